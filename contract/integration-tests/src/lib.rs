@@ -0,0 +1 @@
+//! See `tests/` for the sandbox tests.
@@ -0,0 +1,397 @@
+//! End to end flows on a local sandbox against the mock MPC signer, set with
+//! `set_mpc_contract_id`, and a mock USDC patched in at the supported token id.
+
+use near_workspaces::network::Sandbox;
+use near_workspaces::types::{AccountDetailsPatch, NearToken};
+use near_workspaces::{Account, AccountId, Contract, Worker};
+use serde_json::{json, Value};
+
+const USDC: &str = "usdc.fakes.testnet";
+const NEAR_TOKEN: &str = "near";
+const BANKROLL: u128 = 100_000_000_000_000_000_000_000_000;
+const WAGER: u128 = 1_000_000_000_000_000_000_000_000;
+
+type SpinResults = Vec<Vec<(bool, u8, bool, u8)>>;
+
+struct Env {
+    worker: Worker<Sandbox>,
+    roulette: Contract,
+    signer: Contract,
+    player: Account,
+}
+
+async fn setup() -> anyhow::Result<Env> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account()?;
+
+    let roulette_wasm = near_workspaces::compile_project("..").await?;
+    let signer_wasm = near_workspaces::compile_project("../mocks/mpc").await?;
+    let ft_wasm = near_workspaces::compile_project("../mocks/ft").await?;
+
+    // top level accounts can only be patched in
+    worker
+        .patch(&USDC.parse()?)
+        .account(AccountDetailsPatch::default().balance(NearToken::from_near(10)))
+        .code(&ft_wasm)
+        .transact()
+        .await?;
+    root.call(&USDC.parse()?, "new")
+        .transact()
+        .await?
+        .into_result()?;
+
+    // the contract's bankroll starts at 100 NEAR, back it with real balance
+    let roulette = root
+        .create_subaccount("roulette")
+        .initial_balance(NearToken::from_near(200))
+        .transact()
+        .await?
+        .into_result()?
+        .deploy(&roulette_wasm)
+        .await?
+        .into_result()?;
+    let signer = root
+        .create_subaccount("signer")
+        .initial_balance(NearToken::from_near(10))
+        .transact()
+        .await?
+        .into_result()?
+        .deploy(&signer_wasm)
+        .await?
+        .into_result()?;
+    roulette
+        .call("set_mpc_contract_id")
+        .args_json(json!({ "mpc_contract_id": signer.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let player = root
+        .create_subaccount("player")
+        .initial_balance(NearToken::from_near(50))
+        .transact()
+        .await?
+        .into_result()?;
+    player
+        .call(roulette.id(), "deposit_storage")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(Env {
+        worker,
+        roulette,
+        signer,
+        player,
+    })
+}
+
+fn red(amount: u128) -> Value {
+    json!([[{ "kind": "Red", "amount": amount.to_string(), "number": 0 }]])
+}
+
+async fn view_u128(contract: &Contract, method: &str, args: Value) -> anyhow::Result<u128> {
+    let value: String = contract.view(method).args_json(args).await?.json()?;
+    Ok(value.parse()?)
+}
+
+async fn bankroll(contract: &Contract, token_id: &str) -> anyhow::Result<u128> {
+    view_u128(contract, "get_bankroll", json!({ "token_id": token_id })).await
+}
+
+async fn liability(contract: &Contract, token_id: &str) -> anyhow::Result<u128> {
+    view_u128(contract, "get_liability", json!({ "token_id": token_id })).await
+}
+
+async fn spin_payout(contract: &Contract, spin_id: u64) -> anyhow::Result<u128> {
+    let spin: Value = contract
+        .view("get_spin")
+        .args_json(json!({ "spin_id": spin_id.to_string() }))
+        .await?
+        .json()?;
+    Ok(spin["payout"].as_str().expect("spin settled").parse()?)
+}
+
+async fn ft_balance(worker: &Worker<Sandbox>, account_id: &AccountId) -> anyhow::Result<u128> {
+    let value: String = worker
+        .view(&USDC.parse()?, "ft_balance_of")
+        .args_json(json!({ "account_id": account_id }))
+        .await?
+        .json()?;
+    Ok(value.parse()?)
+}
+
+#[tokio::test]
+async fn near_spin_settles_and_pays_out() -> anyhow::Result<()> {
+    let Env {
+        roulette, player, ..
+    } = setup().await?;
+
+    let outcome = player
+        .call(roulette.id(), "spin_with_near")
+        .args_json(json!({ "spins": red(WAGER) }))
+        .deposit(NearToken::from_yoctonear(WAGER))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+
+    let results: SpinResults = outcome.json()?;
+    assert_eq!(results.len(), 1);
+    let (win, _, red, multiple) = results[0][0];
+    assert_eq!(win, red);
+    assert_eq!(multiple, win as u8);
+
+    let payout = spin_payout(&roulette, 0).await?;
+    assert_eq!(payout, if win { 2 * WAGER } else { 0 });
+    assert_eq!(
+        bankroll(&roulette, NEAR_TOKEN).await?,
+        BANKROLL + WAGER - payout
+    );
+    assert_eq!(liability(&roulette, NEAR_TOKEN).await?, 0);
+
+    let pending: Option<Value> = roulette
+        .view("get_pending_spin")
+        .args_json(json!({ "spin_id": "0" }))
+        .await?
+        .json()?;
+    assert!(pending.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn ft_deposit_spin_and_payout() -> anyhow::Result<()> {
+    let Env {
+        worker,
+        roulette,
+        player,
+        ..
+    } = setup().await?;
+    let root = worker.root_account()?;
+    let usdc: AccountId = USDC.parse()?;
+
+    for (account_id, amount) in [
+        (root.id(), 1_000u128),
+        (player.id(), 100),
+        (roulette.id(), 0),
+    ] {
+        root.call(&usdc, "mint")
+            .args_json(json!({ "account_id": account_id, "amount": amount.to_string() }))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // an empty msg from a player is refunded
+    player
+        .call(&usdc, "ft_transfer_call")
+        .args_json(json!({ "receiver_id": roulette.id(), "amount": "10", "msg": "" }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(ft_balance(&worker, player.id()).await?, 100);
+    assert_eq!(bankroll(&roulette, USDC).await?, 0);
+
+    // an empty msg from the owner funds the bankroll
+    roulette
+        .call("set_owner")
+        .args_json(json!({ "owner_id": root.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    root.call(&usdc, "ft_transfer_call")
+        .args_json(json!({ "receiver_id": roulette.id(), "amount": "1000", "msg": "" }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    assert_eq!(bankroll(&roulette, USDC).await?, 1_000);
+
+    let msg = json!({ "spins": red(10) }).to_string();
+    let outcome = player
+        .call(&usdc, "ft_transfer_call")
+        .args_json(json!({ "receiver_id": roulette.id(), "amount": "10", "msg": msg }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+
+    let payout = spin_payout(&roulette, 0).await?;
+    assert!(payout == 0 || payout == 20);
+    assert_eq!(bankroll(&roulette, USDC).await?, 1_010 - payout);
+    assert_eq!(liability(&roulette, USDC).await?, 0);
+    assert_eq!(ft_balance(&worker, player.id()).await?, 90 + payout);
+    assert_eq!(ft_balance(&worker, roulette.id()).await?, 1_010 - payout);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_signature_refunds_wager() -> anyhow::Result<()> {
+    let Env {
+        roulette,
+        signer,
+        player,
+        ..
+    } = setup().await?;
+
+    signer
+        .call("set_mode")
+        .args_json(json!({ "mode": "Fail" }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance_before = player.view_account().await?.balance;
+    let outcome = player
+        .call(roulette.id(), "spin_with_near")
+        .args_json(json!({ "spins": red(WAGER) }))
+        .deposit(NearToken::from_yoctonear(WAGER))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+
+    let results: SpinResults = outcome.json()?;
+    assert_eq!(results, [[(false, 0, false, 0)]]);
+    assert_eq!(bankroll(&roulette, NEAR_TOKEN).await?, BANKROLL);
+    assert_eq!(liability(&roulette, NEAR_TOKEN).await?, 0);
+
+    // the wager came back, only gas was spent
+    let spent = balance_before.as_yoctonear() - player.view_account().await?.balance.as_yoctonear();
+    assert!(spent < WAGER / 10, "spent {}", spent);
+
+    let claimable = view_u128(
+        &roulette,
+        "get_claimable",
+        json!({ "account_id": player.id(), "token_id": NEAR_TOKEN }),
+    )
+    .await?;
+    assert_eq!(claimable, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fixed_response_decides_the_spin() -> anyhow::Result<()> {
+    let Env {
+        roulette,
+        signer,
+        player,
+        ..
+    } = setup().await?;
+
+    // every random byte is 1, which lands on red 32
+    signer
+        .call("set_mode")
+        .args_json(json!({ "mode": { "Fixed": {
+            "scheme": "Secp256k1",
+            "big_r": { "affine_point": format!("02{}", "00".repeat(32)) },
+            "s": { "scalar": "01".repeat(32) },
+            "recovery_id": 0,
+        } } }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = player
+        .call(roulette.id(), "spin_with_near")
+        .args_json(json!({ "spins": red(WAGER) }))
+        .deposit(NearToken::from_yoctonear(WAGER))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+
+    let results: SpinResults = outcome.json()?;
+    assert_eq!(results, [[(true, 32, true, 1)]]);
+    assert_eq!(spin_payout(&roulette, 0).await?, 2 * WAGER);
+    assert_eq!(bankroll(&roulette, NEAR_TOKEN).await?, BANKROLL - WAGER);
+    Ok(())
+}
+
+#[tokio::test]
+async fn yielded_signature_settles_on_response() -> anyhow::Result<()> {
+    let Env {
+        worker,
+        roulette,
+        signer,
+        player,
+    } = setup().await?;
+
+    signer
+        .call("set_mode")
+        .args_json(json!({ "mode": "Yield" }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let status = player
+        .call(roulette.id(), "spin_with_near")
+        .args_json(json!({ "spins": red(WAGER) }))
+        .deposit(NearToken::from_yoctonear(WAGER))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    // the spin stays pending until the signer responds
+    let mut yields = 0;
+    for _ in 0..20 {
+        yields = signer.view("get_yield_count").await?.json::<u32>()?;
+        if yields > 0 {
+            break;
+        }
+        worker.fast_forward(1).await?;
+    }
+    assert_eq!(yields, 1);
+    assert_eq!(liability(&roulette, NEAR_TOKEN).await?, 2 * WAGER);
+
+    signer
+        .call("respond")
+        .args_json(json!({ "response": null }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = status.await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+    let results: SpinResults = outcome.json()?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(liability(&roulette, NEAR_TOKEN).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn eddsa_spin_records_scheme() -> anyhow::Result<()> {
+    let Env {
+        roulette, player, ..
+    } = setup().await?;
+
+    roulette
+        .call("set_signature_scheme")
+        .args_json(json!({ "scheme": "Eddsa" }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let outcome = player
+        .call(roulette.id(), "spin_with_near")
+        .args_json(json!({ "spins": red(WAGER) }))
+        .deposit(NearToken::from_yoctonear(WAGER))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success(), "{:#?}", outcome);
+
+    let spin: Value = roulette
+        .view("get_spin")
+        .args_json(json!({ "spin_id": "0" }))
+        .await?
+        .json()?;
+    assert_eq!(spin["scheme"], "Eddsa");
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Answers `sign` like the MPC signer of `chain_signature.rs`, without real signatures.
+//! Point the roulette contract at it with `set_mpc_contract_id`.
+
+use std::collections::VecDeque;
+
+use near_sdk::{env, near, require, serde_json, CryptoHash, Gas, GasWeight, PromiseError};
+
+const DATA_ID_REGISTER: u64 = 0;
+const ON_RESPONSE_GAS: Gas = Gas::from_tgas(5);
+
+#[near(serializers = [json])]
+pub enum Payload {
+    Ecdsa(String),
+    Eddsa(String),
+}
+
+#[near(serializers = [json])]
+pub struct SignRequest {
+    pub payload_v2: Payload,
+    pub path: String,
+    pub domain_id: u64,
+}
+
+#[derive(Clone)]
+#[near(serializers = [json, borsh])]
+pub struct SerializableAffinePoint {
+    pub affine_point: String,
+}
+
+#[derive(Clone)]
+#[near(serializers = [json, borsh])]
+pub struct SerializableScalar {
+    pub scalar: String,
+}
+
+/// Tagged by `scheme` like the responses of the real signer.
+#[derive(Clone)]
+#[near(serializers = [json, borsh])]
+#[serde(tag = "scheme")]
+pub enum SignatureResponse {
+    Secp256k1 {
+        big_r: SerializableAffinePoint,
+        s: SerializableScalar,
+        recovery_id: u8,
+    },
+    Ed25519 {
+        signature: Vec<u8>,
+    },
+}
+
+/// How `sign` answers.
+#[derive(Clone)]
+#[near(serializers = [json, borsh])]
+pub enum Mode {
+    /// `s` is the sha256 of the payload and path, a response depends only on the request.
+    /// EdDSA requests get `sha256(s) || s` as signature.
+    Deterministic,
+    /// the same response to every request
+    Fixed(SignatureResponse),
+    /// `sign` panics
+    Fail,
+    /// `sign` yields like the real signer until `respond`, or times out
+    Yield,
+}
+
+/// Request waiting in `Mode::Yield`.
+#[near(serializers = [borsh])]
+pub struct PendingYield {
+    data_id: CryptoHash,
+    response: SignatureResponse, // deterministic one
+}
+
+#[near(contract_state)]
+pub struct Contract {
+    mode: Mode,
+    yields: VecDeque<PendingYield>, // oldest first
+}
+
+impl Default for Contract {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Deterministic,
+            yields: VecDeque::new(),
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    pub fn get_mode(&self) -> Mode {
+        self.mode.clone()
+    }
+
+    /// Open to anyone, this is a test contract.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Requests yielded and not responded to yet.
+    pub fn get_yield_count(&self) -> u32 {
+        self.yields.len() as u32
+    }
+
+    #[payable]
+    pub fn sign(&mut self, request: SignRequest) {
+        let response = match &self.mode {
+            Mode::Deterministic => deterministic_response(&request),
+            Mode::Fixed(response) => response.clone(),
+            Mode::Fail => env::panic_str("signing failed"),
+            Mode::Yield => {
+                let promise = env::promise_yield_create(
+                    "on_response",
+                    &[],
+                    ON_RESPONSE_GAS,
+                    GasWeight(0),
+                    DATA_ID_REGISTER,
+                );
+                let data_id = env::read_register(DATA_ID_REGISTER)
+                    .expect("no data id")
+                    .try_into()
+                    .expect("data id is not a hash");
+                self.yields.push_back(PendingYield {
+                    data_id,
+                    response: deterministic_response(&request),
+                });
+                env::promise_return(promise);
+                return;
+            }
+        };
+        env::value_return(&serde_json::to_vec(&response).unwrap());
+    }
+
+    /// Resumes the oldest yielded request with `response`, its deterministic one by default.
+    pub fn respond(&mut self, response: Option<SignatureResponse>) {
+        let pending = self.yields.pop_front().expect("no yielded request");
+        let response = response.unwrap_or(pending.response);
+
+        // false when the yield already timed out
+        let resumed =
+            env::promise_yield_resume(&pending.data_id, &serde_json::to_vec(&response).unwrap());
+        require!(resumed, "request timed out");
+    }
+
+    #[private]
+    pub fn on_response(
+        &mut self,
+        #[callback_result] response: Result<SignatureResponse, PromiseError>,
+    ) -> SignatureResponse {
+        response.unwrap_or_else(|_| env::panic_str("signature request timed out"))
+    }
+}
+
+fn deterministic_response(request: &SignRequest) -> SignatureResponse {
+    let payload = match &request.payload_v2 {
+        Payload::Ecdsa(payload) | Payload::Eddsa(payload) => payload,
+    };
+    let s = env::sha256_array(&[payload.as_bytes(), request.path.as_bytes()].concat());
+    let r = env::sha256_array(&s);
+
+    match request.payload_v2 {
+        Payload::Ecdsa(_) => SignatureResponse::Secp256k1 {
+            big_r: SerializableAffinePoint {
+                affine_point: format!("02{}", hex::encode(r)),
+            },
+            s: SerializableScalar {
+                scalar: hex::encode(s),
+            },
+            recovery_id: 0,
+        },
+        Payload::Eddsa(_) => SignatureResponse::Ed25519 {
+            signature: [r, s].concat(),
+        },
+    }
+}
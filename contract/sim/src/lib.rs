@@ -0,0 +1,207 @@
+//! Monte Carlo simulation of bet layouts with the contract's `bet_eval`.
+//!
+//! A layout is the `spins` argument of `spin_with_near`, `Vec<Vec<Bet>>`.
+//! One round plays every spin of the layout, a session plays `rounds` rounds
+//! from a starting player bankroll. Random bytes are uniform like the bytes of
+//! the MPC signature, so the small bias of `byte % 37` is simulated too.
+
+use contract_rs::roulette::{bet_eval, bet_legal, Bet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Parses a layout in the JSON format of `spin_with_near`'s `spins`,
+/// rejecting bets the contract would reject as illegal.
+pub fn parse_layout(json: &str) -> Result<Vec<Vec<Bet>>, String> {
+    let layout: Vec<Vec<Bet>> = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    for (spin, bets) in layout.iter().enumerate() {
+        if let Some(bet) = bets.iter().find(|bet| !bet_legal(bet)) {
+            return Err(format!("illegal bet in spin {spin}: {bet:?}"));
+        }
+    }
+    Ok(layout)
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub sessions: u32,
+    pub rounds: u32,    // per session
+    pub bankroll: u128, // player's, at the start of each session
+    pub seed: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub spins: u64,
+    pub wagered: u128,
+    pub paid: u128,
+    /// mean net result of a spin for the player, in yocto
+    pub ev_per_spin: f64,
+    /// `ev_per_spin` per unit wagered, the negated house edge
+    pub ev_per_unit: f64,
+    pub std_dev_per_spin: f64,
+    /// max drawdown of each session, sorted ascending
+    pub drawdowns: Vec<u128>,
+    /// sessions that could not cover the next spin's stake
+    pub ruined: u32,
+}
+
+impl Report {
+    pub fn risk_of_ruin(&self) -> f64 {
+        if self.drawdowns.is_empty() {
+            return 0.0;
+        }
+        self.ruined as f64 / self.drawdowns.len() as f64
+    }
+
+    /// Max drawdown at `percentile` (0..=100) of the sessions.
+    pub fn drawdown_percentile(&self, percentile: u8) -> u128 {
+        if self.drawdowns.is_empty() {
+            return 0;
+        }
+        let index = (self.drawdowns.len() - 1) * percentile.min(100) as usize / 100;
+        self.drawdowns[index]
+    }
+}
+
+/// Total payout (stake included) of `bets` on the random byte `rng_val`.
+pub fn spin_payout(rng_val: u8, bets: &[Bet]) -> u128 {
+    bets.iter()
+        .map(|bet| {
+            let (_, _, _, multiple) = bet_eval(rng_val, bet);
+            match multiple {
+                0 => 0,
+                multiple => bet.amount.as_yoctonear() * (multiple as u128 + 1),
+            }
+        })
+        .sum()
+}
+
+/// Runs `config.sessions` sessions of `layout`, the same seed gives the same report.
+pub fn simulate(layout: &[Vec<Bet>], config: &Config) -> Report {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let stakes: Vec<u128> = layout
+        .iter()
+        .map(|bets| bets.iter().map(|bet| bet.amount.as_yoctonear()).sum())
+        .collect();
+
+    let mut spins: u64 = 0;
+    let mut wagered: u128 = 0;
+    let mut paid: u128 = 0;
+    // running sums of the net result per spin, for the variance
+    let mut net_sum: f64 = 0.0;
+    let mut net_sq_sum: f64 = 0.0;
+    let mut drawdowns = Vec::with_capacity(config.sessions as usize);
+    let mut ruined = 0;
+
+    for _ in 0..config.sessions {
+        let mut balance = config.bankroll;
+        let mut peak = balance;
+        let mut max_drawdown = 0;
+
+        'session: for _ in 0..config.rounds {
+            for (bets, stake) in layout.iter().zip(&stakes) {
+                if balance < *stake {
+                    ruined += 1;
+                    break 'session;
+                }
+                let payout = spin_payout(rng.random::<u8>(), bets);
+                balance = balance - stake + payout;
+
+                spins += 1;
+                wagered += stake;
+                paid += payout;
+                let net = payout as f64 - *stake as f64;
+                net_sum += net;
+                net_sq_sum += net * net;
+
+                peak = peak.max(balance);
+                max_drawdown = max_drawdown.max(peak - balance);
+            }
+        }
+        drawdowns.push(max_drawdown);
+    }
+    drawdowns.sort_unstable();
+
+    let (ev_per_spin, std_dev_per_spin) = match spins {
+        0 => (0.0, 0.0),
+        spins => {
+            let mean = net_sum / spins as f64;
+            let variance = (net_sq_sum / spins as f64 - mean * mean).max(0.0);
+            (mean, variance.sqrt())
+        }
+    };
+    let ev_per_unit = match wagered {
+        0 => 0.0,
+        wagered => (paid as f64 - wagered as f64) / wagered as f64,
+    };
+
+    Report {
+        spins,
+        wagered,
+        paid,
+        ev_per_spin,
+        ev_per_unit,
+        std_dev_per_spin,
+        drawdowns,
+        ruined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: &str = r#"[[{"kind": "Red", "amount": "1000", "number": 0}]]"#;
+
+    fn config(bankroll: u128, seed: u64) -> Config {
+        Config {
+            sessions: 200,
+            rounds: 500,
+            bankroll,
+            seed,
+        }
+    }
+
+    #[test]
+    fn same_seed_same_report() {
+        let layout = parse_layout(RED).unwrap();
+        assert_eq!(
+            simulate(&layout, &config(10_000, 7)),
+            simulate(&layout, &config(10_000, 7))
+        );
+        assert_ne!(
+            simulate(&layout, &config(10_000, 7)),
+            simulate(&layout, &config(10_000, 8))
+        );
+    }
+
+    #[test]
+    fn red_has_negative_ev() {
+        let layout = parse_layout(RED).unwrap();
+        let report = simulate(&layout, &config(u128::MAX / 2, 1));
+
+        assert_eq!(report.ruined, 0);
+        assert_eq!(report.spins, 200 * 500);
+        assert!(report.ev_per_unit < 0.0 && report.ev_per_unit > -0.1);
+        // even money, one unit either way
+        assert!((report.std_dev_per_spin - 1000.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn illegal_bet_is_an_error() {
+        let error =
+            parse_layout(r#"[[{"kind": "Split", "amount": "1000", "number": 200}]]"#).unwrap_err();
+        assert!(error.starts_with("illegal bet in spin 0"));
+        assert!(parse_layout("[[{").is_err());
+    }
+
+    #[test]
+    fn empty_bankroll_is_ruined() {
+        let layout = parse_layout(RED).unwrap();
+        let report = simulate(&layout, &config(0, 1));
+
+        assert_eq!(report.spins, 0);
+        assert_eq!(report.risk_of_ruin(), 1.0);
+        assert_eq!(report.drawdown_percentile(99), 0);
+    }
+}
@@ -0,0 +1,63 @@
+use std::process::exit;
+
+use roulette_sim::{parse_layout, simulate, Config};
+
+const USAGE: &str =
+    "usage: roulette-sim <layout.json> [--sessions N] [--rounds N] [--bankroll YOCTO] [--seed N]";
+
+fn parse_args() -> Result<(String, Config), String> {
+    let mut args = std::env::args().skip(1);
+    let layout_path = args.next().ok_or("missing layout file")?;
+    let mut config = Config {
+        sessions: 10_000,
+        rounds: 100,
+        bankroll: 100_000_000_000_000_000_000_000_000, // 100 NEAR
+        seed: 0,
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {flag}"))?;
+        let invalid = |_| format!("invalid value for {flag}: {value}");
+        match flag.as_str() {
+            "--sessions" => config.sessions = value.parse().map_err(invalid)?,
+            "--rounds" => config.rounds = value.parse().map_err(invalid)?,
+            "--bankroll" => config.bankroll = value.parse().map_err(invalid)?,
+            "--seed" => config.seed = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown flag {flag}")),
+        }
+    }
+    Ok((layout_path, config))
+}
+
+fn main() {
+    let (layout_path, config) = parse_args().unwrap_or_else(|error| {
+        eprintln!("{error}\n{USAGE}");
+        exit(2);
+    });
+    let json = std::fs::read_to_string(&layout_path).unwrap_or_else(|error| {
+        eprintln!("cannot read {layout_path}: {error}");
+        exit(1);
+    });
+    let layout = parse_layout(&json).unwrap_or_else(|error| {
+        eprintln!("invalid layout: {error}");
+        exit(1);
+    });
+
+    let report = simulate(&layout, &config);
+
+    println!("{config:?}");
+    println!("spins:            {}", report.spins);
+    println!("wagered:          {}", report.wagered);
+    println!("paid:             {}", report.paid);
+    println!("ev per spin:      {:.0}", report.ev_per_spin);
+    println!("ev per unit:      {:.5}", report.ev_per_unit);
+    println!("std dev per spin: {:.0}", report.std_dev_per_spin);
+    println!("max drawdown:");
+    for percentile in [50, 90, 95, 99, 100] {
+        println!(
+            "  p{percentile:<3}           {}",
+            report.drawdown_percentile(percentile)
+        );
+    }
+    println!("risk of ruin:     {:.5}", report.risk_of_ruin());
+}
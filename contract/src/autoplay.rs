@@ -0,0 +1,204 @@
+use crate::*;
+
+/// Thresholds on the net result of the spins played so far.
+#[derive(Clone, Debug)]
+#[near(serializers = [json, borsh])]
+pub struct StopConditions {
+    pub stop_loss: Option<U128>,
+    pub take_profit: Option<U128>,
+}
+
+impl StopConditions {
+    /// Whether play stops after wagering `wagered` and being paid `paid`.
+    pub fn reached(&self, wagered: u128, paid: u128) -> bool {
+        let loss_reached = self
+            .stop_loss
+            .is_some_and(|stop_loss| wagered.saturating_sub(paid) >= stop_loss.0);
+        let profit_reached = self
+            .take_profit
+            .is_some_and(|take_profit| paid.saturating_sub(wagered) >= take_profit.0);
+        loss_reached || profit_reached
+    }
+}
+
+/// Plays `bets` up to `spins` times, unplayed spins are refunded.
+/// With a `strategy` the deposit covers the spins all losing and the unstaked part is refunded.
+#[derive(Debug)]
+#[near(serializers = [json])]
+pub struct Autoplay {
+    pub bets: Vec<roulette::Bet>,
+    pub spins: u8,
+    pub stop_loss: Option<U128>,
+    pub take_profit: Option<U128>,
+    pub strategy: Option<roulette::Strategy>,
+}
+
+impl Autoplay {
+    /// Spins to deposit for, options without gas or payout settings.
+    pub fn into_spins(self) -> (Vec<Vec<roulette::Bet>>, SpinOptions) {
+        require!(!self.bets.is_empty(), "autoplay without bets");
+        require!(self.spins > 0, "autoplay without spins");
+
+        let spins = match self.strategy {
+            Some(strategy) => strategy.worst_case(&self.bets, self.spins),
+            None => vec![self.bets; self.spins as usize],
+        };
+        let options = SpinOptions {
+            stop: Some(StopConditions {
+                stop_loss: self.stop_loss,
+                take_profit: self.take_profit,
+            }),
+            strategy: self.strategy,
+            ..Default::default()
+        };
+        (spins, options)
+    }
+}
+
+#[near]
+impl Contract {
+    /// Attached deposit covers all `autoplay.spins`, see `spin_with_near` for the other arguments.
+    #[payable]
+    pub fn autoplay_with_near(
+        &mut self,
+        autoplay: Autoplay,
+        callback_tgas: Option<u8>,
+        payout_to: Option<payout::PayoutTo>,
+        referrer_id: Option<AccountId>,
+    ) -> Promise {
+        let amount = env::attached_deposit();
+        let sender_id = env::predecessor_account_id();
+        self.register_referrer(&sender_id, referrer_id);
+
+        let (spins, options) = autoplay.into_spins();
+        self.spin(
+            spins,
+            sender_id,
+            amount.as_yoctonear(),
+            bankroll::near_token_id(),
+            SpinOptions {
+                callback_tgas,
+                payout_to,
+                ..options
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit_storage, setup_with_deposit, signature, BANKROLL, WAGER};
+    use near_sdk::test_utils::accounts;
+
+    fn autoplay_red(
+        contract: &mut Contract,
+        spins: u8,
+        deposit: u128,
+        stop_loss: Option<u128>,
+        strategy: Option<roulette::Strategy>,
+    ) {
+        deposit_storage(contract, accounts(1));
+        setup_with_deposit(accounts(1), deposit);
+        let autoplay = Autoplay {
+            bets: vec![roulette::Bet {
+                kind: roulette::BetKind::Red,
+                amount: NearToken::from_yoctonear(WAGER),
+                number: 0,
+            }],
+            spins,
+            stop_loss: stop_loss.map(U128),
+            take_profit: None,
+            strategy,
+        };
+        contract.autoplay_with_near(autoplay, None, None, None);
+    }
+
+    #[test]
+    fn stop_conditions() {
+        let stop = StopConditions {
+            stop_loss: Some(U128(100)),
+            take_profit: Some(U128(50)),
+        };
+        assert!(!stop.reached(0, 0));
+        assert!(!stop.reached(99, 0));
+        assert!(stop.reached(100, 0));
+        assert!(!stop.reached(100, 149));
+        assert!(stop.reached(100, 150));
+
+        let no_stop = StopConditions {
+            stop_loss: None,
+            take_profit: None,
+        };
+        assert!(!no_stop.reached(1_000, 0));
+        assert!(!no_stop.reached(0, 1_000));
+    }
+
+    #[test]
+    fn stop_loss_refunds_unplayed_spins() {
+        let mut contract = Contract::default();
+        autoplay_red(&mut contract, 5, 5 * WAGER, Some(2 * WAGER), None);
+
+        let results = contract.mpc_callback(Ok(signature([0; 32])), 0);
+
+        let near_token_id = bankroll::near_token_id();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            contract.get_bankroll(near_token_id.clone()).0,
+            BANKROLL + 2 * WAGER
+        );
+        assert_eq!(contract.get_liability(near_token_id.clone()).0, 0);
+        let stats = contract.stats_v2(near_token_id);
+        assert_eq!(stats.spins.0, 2);
+        assert_eq!(stats.wagered.0, 2 * WAGER);
+        assert_eq!(contract.stats().0 .0, 2);
+        assert_eq!(contract.stats().1 .0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "deposit != bet amount")]
+    fn strategy_deposit_covers_worst_case() {
+        let mut contract = Contract::default();
+        // martingale stakes up to 1 + 2 + 4
+        autoplay_red(
+            &mut contract,
+            3,
+            3 * WAGER,
+            None,
+            Some(roulette::Strategy::Martingale),
+        );
+    }
+
+    #[test]
+    fn strategy_refunds_unstaked_deposit() {
+        let mut contract = Contract::default();
+        autoplay_red(
+            &mut contract,
+            3,
+            7 * WAGER,
+            None,
+            Some(roulette::Strategy::Martingale),
+        );
+
+        // win 1, back to 1 and lose, double to 2 and lose
+        let mut s = [0; 32];
+        s[0] = 1;
+        let results = contract.mpc_callback(Ok(signature(s)), 0);
+
+        let near_token_id = bankroll::near_token_id();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            contract.get_spin(U64(2)).unwrap().bets[0]
+                .amount
+                .as_yoctonear(),
+            2 * WAGER
+        );
+        // staked 4, paid 2 and refunded 3 of the 7 deposited
+        assert_eq!(
+            contract.get_bankroll(near_token_id.clone()).0,
+            BANKROLL + 2 * WAGER
+        );
+        assert_eq!(contract.get_liability(near_token_id.clone()).0, 0);
+        assert_eq!(contract.stats_v2(near_token_id).wagered.0, 4 * WAGER);
+    }
+}
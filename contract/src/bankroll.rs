@@ -0,0 +1,131 @@
+use crate::*;
+
+const RESOLVE_WITHDRAW_GAS: Gas = Gas::from_tgas(5);
+
+/// Token id used for native NEAR in per token maps.
+pub fn near_token_id() -> AccountId {
+    AccountId::from_str("near").unwrap()
+}
+
+#[near]
+impl Contract {
+    pub fn get_bankroll(&self, token_id: AccountId) -> U128 {
+        U128(*self.bankroll.get(&token_id).unwrap_or(&0))
+    }
+
+    /// Max payout owed to spins waiting on their signature.
+    pub fn get_liability(&self, token_id: AccountId) -> U128 {
+        U128(*self.liabilities.get(&token_id).unwrap_or(&0))
+    }
+
+    /// Adds the attached NEAR to the house bankroll, only the owner funds it.
+    /// FTs are added with `ft_transfer_call` and an empty `msg`.
+    #[payable]
+    pub fn fund_bankroll(&mut self) {
+        self.assert_owner();
+        let amount = env::attached_deposit().as_yoctonear();
+        self.add_bankroll(&near_token_id(), amount);
+    }
+
+    /// Withdraws house funds not locked by spins in flight.
+    pub fn withdraw_bankroll(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+    ) -> Promise {
+        self.assert_owner();
+        self.assert_withdrawals_not_paused();
+        require!(
+            self.free_bankroll(&token_id) >= amount.0,
+            "amount exceeds free bankroll"
+        );
+        let bankroll = self.get_bankroll(token_id.clone()).0;
+        self.bankroll.insert(token_id.clone(), bankroll - amount.0);
+
+        transfer(token_id.clone(), receiver_id, amount.0).then(
+            my_contract::ext(env::current_account_id())
+                .with_static_gas(RESOLVE_WITHDRAW_GAS)
+                .resolve_withdraw_bankroll(token_id, amount),
+        )
+    }
+
+    /// Puts a failed withdrawal back into the bankroll.
+    #[private]
+    pub fn resolve_withdraw_bankroll(
+        &mut self,
+        #[callback_result] call_result: Result<(), PromiseError>,
+        token_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        if call_result.is_ok() {
+            return true;
+        }
+
+        log!(
+            "withdrawal of {} {} failed, added back to bankroll",
+            amount.0,
+            token_id
+        );
+        self.add_bankroll(&token_id, amount.0);
+        false
+    }
+}
+
+/// Sends `amount` of NEAR or of the FT `token_id`.
+pub fn transfer(token_id: AccountId, receiver_id: AccountId, amount: u128) -> Promise {
+    match token_id.as_str() {
+        "near" => Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount)),
+        _ => ft::ft_contract::ext(token_id)
+            .with_static_gas(gas::FT_TRANSFER_GAS)
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .ft_transfer(receiver_id, U128(amount), None),
+    }
+}
+
+// internal
+
+impl Contract {
+    pub(crate) fn add_bankroll(&mut self, token_id: &AccountId, amount: u128) {
+        let bankroll = self.bankroll.entry(token_id.clone()).or_insert(0);
+        *bankroll = bankroll.checked_add(amount).expect("bankroll overflow");
+    }
+
+    /// Bankroll not locked by spins in flight.
+    pub(crate) fn free_bankroll(&self, token_id: &AccountId) -> u128 {
+        let bankroll = self.get_bankroll(token_id.clone()).0;
+        bankroll.saturating_sub(self.get_liability(token_id.clone()).0)
+    }
+
+    /// Moves up to `amount` of the free bankroll out for player rewards,
+    /// returns the amount moved.
+    pub(crate) fn reserve_bankroll(&mut self, token_id: &AccountId, amount: u128) -> u128 {
+        let reserved = amount.min(self.free_bankroll(token_id));
+        if let Some(bankroll) = self.bankroll.get_mut(token_id) {
+            *bankroll -= reserved;
+        }
+        reserved
+    }
+
+    /// Takes the wager into the bankroll and reserves the max payout of the spins.
+    pub(crate) fn lock_liability(&mut self, token_id: &AccountId, wager: u128, liability: u128) {
+        self.add_bankroll(token_id, wager);
+
+        let locked = self.liabilities.entry(token_id.clone()).or_insert(0);
+        *locked = locked.checked_add(liability).expect("liability overflow");
+
+        require!(
+            *locked <= *self.bankroll.get(token_id).unwrap_or(&0),
+            "bankroll too low for bets"
+        );
+    }
+
+    /// Releases a reserved liability and pays `payout` out of the bankroll.
+    pub(crate) fn settle_liability(&mut self, token_id: &AccountId, liability: u128, payout: u128) {
+        let locked = self.liabilities.entry(token_id.clone()).or_insert(0);
+        *locked = locked.saturating_sub(liability);
+
+        let bankroll = self.bankroll.entry(token_id.clone()).or_insert(0);
+        *bankroll = bankroll.checked_sub(payout).expect("bankroll empty");
+    }
+}
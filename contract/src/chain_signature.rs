@@ -1,4 +1,67 @@
 use crate::*;
+use omni_transaction::signer::types::SignatureResponse;
+
+/// Signature scheme, and MPC domain, the spin randomness comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[near(serializers = [json, borsh])]
+pub enum SignatureScheme {
+    Ecdsa,
+    Eddsa,
+}
+
+/// `sign` response of the MPC signer, ECDSA `big_r`/`s` or Ed25519 signature bytes.
+#[derive(serde::Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum SignResponse {
+    Ecdsa(SignatureResponse),
+    Eddsa { signature: Vec<u8> },
+}
+
+impl SignResponse {
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            SignResponse::Ecdsa(_) => SignatureScheme::Ecdsa,
+            SignResponse::Eddsa { .. } => SignatureScheme::Eddsa,
+        }
+    }
+
+    /// ECDSA (r, s, recovery id), `None` for an Ed25519 signature.
+    pub fn ecdsa_signature(&self) -> Option<(Vec<u8>, Vec<u8>, u8)> {
+        let SignResponse::Ecdsa(response) = self else {
+            return None;
+        };
+        let mut r_bytes = hex::decode(&response.big_r.affine_point).expect("r_bytes failed");
+        // first r_byte is compression flag
+        r_bytes.remove(0);
+        let s_bytes = hex::decode(&response.s.scalar).expect("s_bytes failed");
+        Some((r_bytes, s_bytes, response.recovery_id))
+    }
+
+    /// 64 random bytes, byte i decides spin i.
+    pub fn entropy(self) -> Vec<u8> {
+        match &self {
+            SignResponse::Ecdsa(_) => {
+                let (r_bytes, mut s_bytes, _) = self.ecdsa_signature().unwrap();
+                // all byte of s_bytes should be random for signature s is scalar value
+                s_bytes.extend(r_bytes);
+                s_bytes
+            }
+            SignResponse::Eddsa { signature } => {
+                require!(signature.len() == 64, "eddsa signature is not 64 bytes");
+                // S is reduced mod the group order so its top bits are not random, hash it all
+                env::keccak512(signature)
+            }
+        }
+    }
+}
+
+/// Signer change staged by the owner, it decides spin outcomes so it waits like an upgrade.
+#[near(serializers = [json, borsh])]
+pub struct StagedSigner {
+    pub mpc_contract_id: AccountId,
+    pub effective_at: U64, // block timestamp, ns
+    pub approved: bool,
+}
 
 #[near(serializers = [json, borsh])]
 pub enum Payload {
@@ -18,13 +81,146 @@ trait MPCContract {
     fn sign(&self, request: SignRequest);
 }
 
-const GAS: Gas = Gas::from_tgas(10);
+pub const SIGN_GAS: Gas = Gas::from_tgas(10);
+/// Derivation path purpose of spin randomness.
+pub const SPIN_PURPOSE: &str = "spin";
 const ATTACHED_DEPOSIT: NearToken = NearToken::from_yoctonear(1);
 
-pub fn internal_request_signature(path: String, payload: String, key_type: String) -> Promise {
-    let (payload_v2, domain_id) = match key_type.as_str() {
-        "Eddsa" => (Payload::Eddsa(payload), 1),
-        _ => (Payload::Ecdsa(payload), 0),
+/// Signer for the network the contract is deployed on.
+pub fn default_mpc_contract_id() -> AccountId {
+    if env::current_account_id().as_str().contains("testnet") {
+        "v1.signer-prod.testnet".parse().unwrap()
+    } else {
+        "v1.signer".parse().unwrap()
+    }
+}
+
+#[near]
+impl Contract {
+    pub fn get_mpc_contract_id(&self) -> AccountId {
+        self.mpc_contract_id.clone()
+    }
+
+    pub fn get_staged_mpc_contract_id(&self) -> Option<&StagedSigner> {
+        self.staged_mpc_contract_id.as_ref()
+    }
+
+    /// Points spins at another signer, e.g. the mock in `mocks/mpc` for local and staging deployments.
+    /// Before the first spin it applies at once. Afterwards it is staged like an upgrade and
+    /// applied with `apply_mpc_contract_id` once the upgrade delay has passed and,
+    /// when there is an upgrade approver, it approved the signer.
+    pub fn set_mpc_contract_id(&mut self, mpc_contract_id: AccountId) {
+        self.assert_owner();
+        if self.next_spin_id == 0 {
+            self.change_mpc_contract_id(mpc_contract_id);
+            return;
+        }
+
+        let effective_at = env::block_timestamp() + self.upgrade_delay();
+        log!(
+            "staged signer {}, effective at {}",
+            mpc_contract_id,
+            effective_at
+        );
+        self.staged_mpc_contract_id = Some(StagedSigner {
+            mpc_contract_id,
+            effective_at: U64(effective_at),
+            approved: false,
+        });
+    }
+
+    /// Approves the staged signer, `mpc_contract_id` must match it.
+    pub fn approve_mpc_contract_id(&mut self, mpc_contract_id: AccountId) {
+        require!(
+            self.upgrade_approver_id.as_ref() == Some(&env::predecessor_account_id()),
+            "only upgrade approver"
+        );
+        let staged = self
+            .staged_mpc_contract_id
+            .as_mut()
+            .expect("no staged signer");
+        require!(
+            staged.mpc_contract_id == mpc_contract_id,
+            "signer does not match staged signer"
+        );
+        staged.approved = true;
+    }
+
+    pub fn cancel_mpc_contract_id(&mut self) {
+        self.assert_owner();
+        self.staged_mpc_contract_id = None;
+    }
+
+    /// Switches new spins to the staged signer, spins in flight keep theirs.
+    pub fn apply_mpc_contract_id(&mut self) {
+        self.assert_owner();
+        let staged = self
+            .staged_mpc_contract_id
+            .take()
+            .expect("no staged signer");
+        require!(
+            env::block_timestamp() >= staged.effective_at.0,
+            "upgrade delay not passed"
+        );
+        require!(
+            staged.approved || self.upgrade_approver_id.is_none(),
+            "signer not approved"
+        );
+        self.change_mpc_contract_id(staged.mpc_contract_id);
+    }
+
+    pub fn get_signature_scheme(&self) -> SignatureScheme {
+        self.signature_scheme
+    }
+
+    /// Scheme of the signatures requested for new spins, spins in flight keep theirs.
+    pub fn set_signature_scheme(&mut self, scheme: SignatureScheme) {
+        self.assert_owner();
+        self.signature_scheme = scheme;
+    }
+}
+
+// internal
+
+impl Contract {
+    fn change_mpc_contract_id(&mut self, mpc_contract_id: AccountId) {
+        log!(
+            "signer changed from {} to {}",
+            self.mpc_contract_id,
+            mpc_contract_id
+        );
+        self.mpc_contract_id = mpc_contract_id;
+    }
+}
+
+/// MPC derivation path of this contract for `purpose`, keys of different purposes never overlap.
+pub fn derivation_path(purpose: &str) -> String {
+    format!("{}/{}", env::current_account_id(), purpose)
+}
+
+/// Hash of everything a spin is decided for, so no two spins request the same signature.
+pub fn spin_payload(
+    spin_id: u64,
+    account_id: &AccountId,
+    spins: &[Vec<roulette::Bet>],
+    block_height: u64,
+    random_seed: [u8; 32],
+) -> [u8; 32] {
+    let preimage =
+        near_sdk::borsh::to_vec(&(spin_id, account_id, spins, block_height, random_seed))
+            .expect("payload serialization failed");
+    env::sha256_array(&preimage)
+}
+
+pub fn internal_request_signature(
+    mpc_contract_id: AccountId,
+    path: String,
+    payload: String,
+    scheme: SignatureScheme,
+) -> Promise {
+    let (payload_v2, domain_id) = match scheme {
+        SignatureScheme::Ecdsa => (Payload::Ecdsa(payload), 0),
+        SignatureScheme::Eddsa => (Payload::Eddsa(payload), 1),
     };
 
     let request = SignRequest {
@@ -33,14 +229,126 @@ pub fn internal_request_signature(path: String, payload: String, key_type: Strin
         domain_id,
     };
 
-    let mpc_contract_id = if env::current_account_id().as_str().contains("testnet") {
-        "v1.signer-prod.testnet"
-    } else {
-        "v1.signer"
-    };
-
-    mpc_contract::ext(mpc_contract_id.parse().unwrap())
-        .with_static_gas(GAS)
+    mpc_contract::ext(mpc_contract_id)
+        .with_static_gas(SIGN_GAS)
         .with_attached_deposit(ATTACHED_DEPOSIT)
         .sign(request)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{context, setup};
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    /// Owner calls at `block_timestamp`.
+    fn setup_at_time(block_timestamp: u64) {
+        testing_env!(context(accounts(0))
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    /// Contract that already took a spin.
+    fn contract_after_spin() -> Contract {
+        Contract {
+            next_spin_id: 1,
+            ..Default::default()
+        }
+    }
+
+    fn signer() -> AccountId {
+        "mock-signer.test.near".parse().unwrap()
+    }
+
+    fn spins(amount: u128) -> Vec<Vec<roulette::Bet>> {
+        vec![vec![roulette::Bet {
+            kind: roulette::BetKind::Red,
+            amount: NearToken::from_yoctonear(amount),
+            number: 0,
+        }]]
+    }
+
+    #[test]
+    fn spin_payload_binds_every_input() {
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        let payload = spin_payload(1, &account_id, &spins(10), 100, [7; 32]);
+
+        assert_eq!(
+            payload,
+            spin_payload(1, &account_id, &spins(10), 100, [7; 32])
+        );
+        for other in [
+            spin_payload(2, &account_id, &spins(10), 100, [7; 32]),
+            spin_payload(1, &"bob.near".parse().unwrap(), &spins(10), 100, [7; 32]),
+            spin_payload(1, &account_id, &spins(11), 100, [7; 32]),
+            spin_payload(1, &account_id, &spins(10), 101, [7; 32]),
+            spin_payload(1, &account_id, &spins(10), 100, [8; 32]),
+        ] {
+            assert_ne!(payload, other);
+        }
+    }
+
+    #[test]
+    fn signer_change_waits_for_upgrade_delay() {
+        setup_at_time(1_000);
+        let mut contract = contract_after_spin();
+        contract.set_mpc_contract_id(signer());
+
+        assert_eq!(contract.get_mpc_contract_id(), default_mpc_contract_id());
+        let staged = contract.get_staged_mpc_contract_id().unwrap();
+        assert_eq!(staged.mpc_contract_id, signer());
+        assert_eq!(
+            staged.effective_at.0,
+            1_000 + upgrade::DEFAULT_UPGRADE_DELAY_NS
+        );
+
+        setup_at_time(1_000 + upgrade::DEFAULT_UPGRADE_DELAY_NS);
+        contract.apply_mpc_contract_id();
+
+        assert_eq!(contract.get_mpc_contract_id(), signer());
+        assert!(contract.get_staged_mpc_contract_id().is_none());
+        assert_eq!(
+            get_logs(),
+            [format!("signer changed from v1.signer to {}", signer())]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "upgrade delay not passed")]
+    fn signer_change_before_delay() {
+        setup_at_time(1_000);
+        let mut contract = contract_after_spin();
+        contract.set_mpc_contract_id(signer());
+
+        setup_at_time(999 + upgrade::DEFAULT_UPGRADE_DELAY_NS);
+        contract.apply_mpc_contract_id();
+    }
+
+    #[test]
+    fn approver_approves_signer_change() {
+        setup_at_time(1_000);
+        let mut contract = contract_after_spin();
+        contract.set_upgrade_approver(Some(accounts(1)));
+        contract.set_mpc_contract_id(signer());
+
+        setup(accounts(1));
+        contract.approve_mpc_contract_id(signer());
+
+        setup_at_time(1_000 + upgrade::DEFAULT_UPGRADE_DELAY_NS);
+        contract.apply_mpc_contract_id();
+        assert_eq!(contract.get_mpc_contract_id(), signer());
+    }
+
+    #[test]
+    #[should_panic(expected = "signer not approved")]
+    fn signer_change_needs_approval() {
+        setup_at_time(1_000);
+        let mut contract = contract_after_spin();
+        contract.set_upgrade_approver(Some(accounts(1)));
+        contract.set_mpc_contract_id(signer());
+
+        setup_at_time(1_000 + upgrade::DEFAULT_UPGRADE_DELAY_NS);
+        contract.apply_mpc_contract_id();
+    }
+}
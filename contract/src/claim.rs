@@ -0,0 +1,138 @@
+use crate::*;
+
+pub const RESOLVE_PAYOUT_GAS: Gas = Gas::from_tgas(5);
+
+#[near]
+impl Contract {
+    pub fn get_claimable(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128(*self.claimable.get(&(account_id, token_id)).unwrap_or(&0))
+    }
+
+    pub fn get_holding_winnings(&self, account_id: AccountId) -> bool {
+        self.holding_winnings.contains(&account_id)
+    }
+
+    /// Credits the caller's winnings to claimable instead of paying them,
+    /// to claim them later or cash them out with `cash_out_evm`.
+    pub fn set_holding_winnings(&mut self, holding: bool) {
+        let account_id = env::predecessor_account_id();
+        if holding {
+            self.holding_winnings.insert(account_id);
+        } else {
+            self.holding_winnings.remove(&account_id);
+        }
+    }
+
+    /// Retries payouts that failed, e.g. when the player was not registered with the token.
+    pub fn claim_winnings(&mut self, token_id: AccountId) -> Promise {
+        self.assert_withdrawals_not_paused();
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .claimable
+            .remove(&(account_id.clone(), token_id.clone()))
+            .expect("nothing to claim");
+
+        self.pay_out(token_id, account_id, amount)
+            .expect("nothing to claim")
+    }
+
+    /// Credits a failed payout to the receiver's claimable winnings.
+    #[private]
+    pub fn resolve_payout(
+        &mut self,
+        #[callback_result] call_result: Result<(), PromiseError>,
+        receiver_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        if call_result.is_ok() {
+            return true;
+        }
+
+        log!(
+            "payout of {} {} to {} failed, added to claimable",
+            amount.0,
+            token_id,
+            receiver_id
+        );
+        self.add_claimable(receiver_id, token_id, amount.0);
+        false
+    }
+}
+
+// internal
+
+impl Contract {
+    pub(crate) fn add_claimable(
+        &mut self,
+        account_id: AccountId,
+        token_id: AccountId,
+        amount: u128,
+    ) {
+        let claimable = self.claimable.entry((account_id, token_id)).or_insert(0);
+        *claimable = claimable.checked_add(amount).expect("claimable overflow");
+    }
+
+    /// Transfers `amount` to a player, failed transfers become claimable.
+    pub(crate) fn pay_out(
+        &self,
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: u128,
+    ) -> Option<Promise> {
+        if amount == 0 {
+            return None;
+        }
+
+        Some(
+            bankroll::transfer(token_id.clone(), receiver_id.clone(), amount).then(
+                my_contract::ext(env::current_account_id())
+                    .with_static_gas(RESOLVE_PAYOUT_GAS)
+                    .resolve_payout(receiver_id, token_id, U128(amount)),
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::setup;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn failed_payout_becomes_claimable() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let token_id = accounts(2);
+
+        assert!(contract.resolve_payout(Ok(()), accounts(1), token_id.clone(), U128(5)));
+        assert_eq!(contract.get_claimable(accounts(1), token_id.clone()).0, 0);
+
+        assert!(!contract.resolve_payout(
+            Err(PromiseError::Failed),
+            accounts(1),
+            token_id.clone(),
+            U128(5)
+        ));
+        contract.resolve_payout(
+            Err(PromiseError::Failed),
+            accounts(1),
+            token_id.clone(),
+            U128(7),
+        );
+        assert_eq!(contract.get_claimable(accounts(1), token_id.clone()).0, 12);
+
+        setup(accounts(1));
+        contract.claim_winnings(token_id.clone());
+        assert_eq!(contract.get_claimable(accounts(1), token_id).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "nothing to claim")]
+    fn claim_without_winnings() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        contract.claim_winnings(accounts(2));
+    }
+}
@@ -0,0 +1,751 @@
+use crate::*;
+use omni_transaction::evm::types::Signature;
+use omni_transaction::evm::EVMTransaction;
+
+/// Derivation path purpose of the house address paying out on EVM chains.
+pub const EVM_PAYOUT_PURPOSE: &str = "evm-payout";
+const RESOLVE_EVM_PAYOUT_GAS: Gas = Gas::from_tgas(10);
+// transfer(address,uint256)
+const ERC20_TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// Fixed point scale of `EvmChain::rate`.
+pub const RATE_SCALE: u128 = 1_000_000_000_000;
+
+/// Where claimable winnings of `token_id` are cashed out on an EVM chain.
+/// `rate` is the whole EVM asset paid per whole token, times `RATE_SCALE`.
+#[near(serializers = [json, borsh])]
+pub struct EvmChain {
+    pub token_id: AccountId,
+    pub token_decimals: u8,
+    pub erc20: Option<String>, // token contract, the native coin when not set
+    pub decimals: u8,          // of the EVM asset
+    pub rate: U128,
+    pub nonce: U64, // of the next payout transaction
+    #[serde(default)]
+    pub free_nonces: Vec<U64>, // below `nonce` and unused after failed signatures, ascending
+    #[serde(default)]
+    pub signed_nonces: Vec<U64>, // of signed payouts not confirmed by a resync, ascending
+    pub gas_limit: U128,
+    pub max_fee_per_gas: U128,
+    pub max_priority_fee_per_gas: U128,
+}
+
+impl EvmChain {
+    /// Makes `nonce` of a payout that was not signed available to the next payout.
+    pub fn free_nonce(&mut self, nonce: U64) {
+        if nonce.0 >= self.nonce.0 {
+            // resynced past it
+            return;
+        }
+        let index = self
+            .free_nonces
+            .binary_search_by_key(&nonce.0, |free| free.0)
+            .unwrap_or_else(|index| index);
+        self.free_nonces.insert(index, nonce);
+
+        // free nonces right below `nonce` are not gaps
+        while self
+            .free_nonces
+            .last()
+            .is_some_and(|free| free.0 + 1 == self.nonce.0)
+        {
+            self.nonce = self.free_nonces.pop().unwrap();
+        }
+    }
+
+    /// EVM asset base units paid for `amount` base units of `token_id`, rounded down.
+    pub fn evm_amount(&self, amount: u128) -> u128 {
+        let scale = |decimals: u8| {
+            10u128
+                .checked_pow(decimals as u32)
+                .expect("decimals too large")
+        };
+        let amount = if self.decimals >= self.token_decimals {
+            amount.checked_mul(scale(self.decimals - self.token_decimals))
+        } else {
+            Some(amount / scale(self.token_decimals - self.decimals))
+        };
+        amount
+            .and_then(|amount| amount.checked_mul(self.rate.0))
+            .expect("amount overflow")
+            / RATE_SCALE
+    }
+}
+
+/// Unsigned EIP-1559 payout, passed to `resolve_evm_payout` while it is being signed.
+#[derive(Clone)]
+#[near(serializers = [json, borsh])]
+pub struct EvmPayout {
+    pub token_id: AccountId, // of the claimable winnings cashed out
+    pub token_amount: U128,
+    pub chain_id: U64,
+    pub nonce: U64,
+    pub to: String,
+    pub amount: U128, // of the EVM asset
+    pub erc20: Option<String>,
+    pub gas_limit: U128,
+    pub max_fee_per_gas: U128,
+    pub max_priority_fee_per_gas: U128,
+}
+
+/// Signed payout kept until a nonce resync, to re-sign it with new fees
+/// or give it back to the player when it was never mined.
+#[near(serializers = [json, borsh])]
+pub struct SignedEvmPayout {
+    pub account_id: AccountId,
+    pub payout: EvmPayout,
+}
+
+impl EvmPayout {
+    pub fn transaction(&self) -> EVMTransaction {
+        let to = parse_address(&self.to);
+        let (to, value, input) = match &self.erc20 {
+            Some(erc20) => {
+                let mut input = ERC20_TRANSFER_SELECTOR.to_vec();
+                input.extend([0; 12]);
+                input.extend(to);
+                input.extend([0; 16]);
+                input.extend(self.amount.0.to_be_bytes());
+                (parse_address(erc20), 0, input)
+            }
+            None => (to, self.amount.0, vec![]),
+        };
+
+        EVMTransaction {
+            chain_id: self.chain_id.0,
+            nonce: self.nonce.0,
+            to: Some(to),
+            value,
+            input,
+            gas_limit: self.gas_limit.0,
+            max_fee_per_gas: self.max_fee_per_gas.0,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.0,
+            access_list: vec![],
+        }
+    }
+}
+
+/// 20 byte address from hex, with or without `0x`.
+pub fn parse_address(address: &str) -> [u8; 20] {
+    let bytes = hex::decode(address.strip_prefix("0x").unwrap_or(address))
+        .ok()
+        .filter(|bytes| bytes.len() == 20)
+        .unwrap_or_else(|| env::panic_str("invalid evm address"));
+    bytes.try_into().unwrap()
+}
+
+/// Big-endian integer bytes without leading zeros, as RLP expects them.
+fn trim_leading_zeros(mut bytes: Vec<u8>) -> Vec<u8> {
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    bytes.drain(..zeros);
+    bytes
+}
+
+/// Raw signed transaction of `payout` from an ECDSA signature of its hash.
+pub fn signed_transaction(payout: &EvmPayout, response: chain_signature::SignResponse) -> Vec<u8> {
+    let (r, s, recovery_id) = response
+        .ecdsa_signature()
+        .unwrap_or_else(|| env::panic_str("evm payouts need an ecdsa signature"));
+
+    payout.transaction().build_with_signature(&Signature {
+        v: recovery_id as u64,
+        r: trim_leading_zeros(r),
+        s: trim_leading_zeros(s),
+    })
+}
+
+#[near]
+impl Contract {
+    pub fn get_evm_chain(&self, chain_id: U64) -> Option<&EvmChain> {
+        self.evm_chains.get(&chain_id.0)
+    }
+
+    /// Derivation path of the house address, fund it on every chain in `evm_chains`.
+    pub fn get_evm_payout_path(&self) -> String {
+        chain_signature::derivation_path(EVM_PAYOUT_PURPOSE)
+    }
+
+    pub fn get_evm_payout(&self, chain_id: U64, nonce: U64) -> Option<&SignedEvmPayout> {
+        self.evm_payouts.get(&(chain_id.0, nonce.0))
+    }
+
+    /// Signed payouts of a configured chain are kept, whatever `signed_nonces` says.
+    pub fn set_evm_chain(&mut self, chain_id: U64, chain: EvmChain) {
+        self.assert_owner();
+        if let Some(erc20) = &chain.erc20 {
+            parse_address(erc20);
+        }
+        require!(chain.rate.0 > 0, "rate must be positive");
+        chain.evm_amount(1);
+        let signed_nonces = self
+            .evm_chains
+            .get(&chain_id.0)
+            .map_or(vec![], |current| current.signed_nonces.clone());
+        self.evm_chains.insert(
+            chain_id.0,
+            EvmChain {
+                signed_nonces,
+                ..chain
+            },
+        );
+    }
+
+    /// Rate of the cash outs from now on, see `EvmChain`.
+    pub fn set_evm_rate(&mut self, chain_id: U64, rate: U128) {
+        self.assert_owner();
+        require!(rate.0 > 0, "rate must be positive");
+        let chain = self.evm_chains.get_mut(&chain_id.0).expect("unknown chain");
+        chain.rate = rate;
+    }
+
+    pub fn remove_evm_chain(&mut self, chain_id: U64) {
+        self.assert_owner();
+        require!(
+            self.evm_chains
+                .get(&chain_id.0)
+                .is_none_or(|chain| chain.signed_nonces.is_empty()),
+            "unconfirmed payouts, resync the nonce first"
+        );
+        self.evm_chains.remove(&chain_id.0);
+    }
+
+    /// Fees of the payouts signed from now on.
+    pub fn set_evm_gas_price(
+        &mut self,
+        chain_id: U64,
+        max_fee_per_gas: U128,
+        max_priority_fee_per_gas: U128,
+    ) {
+        self.assert_owner();
+        require!(
+            max_priority_fee_per_gas.0 <= max_fee_per_gas.0,
+            "priority fee above max fee"
+        );
+        let chain = self.evm_chains.get_mut(&chain_id.0).expect("unknown chain");
+        chain.max_fee_per_gas = max_fee_per_gas;
+        chain.max_priority_fee_per_gas = max_priority_fee_per_gas;
+    }
+
+    /// Resyncs the nonce with the chain, `nonce` is the chain's next one. Signed payouts
+    /// below it were mined and are forgotten, the others were never mined and go back to
+    /// the players' claimable winnings. Forgets the free nonces.
+    pub fn set_evm_nonce(&mut self, chain_id: U64, nonce: U64) {
+        self.assert_owner();
+        let chain = self.evm_chains.get_mut(&chain_id.0).expect("unknown chain");
+        chain.nonce = nonce;
+        chain.free_nonces.clear();
+        let signed_nonces = std::mem::take(&mut chain.signed_nonces);
+
+        for signed_nonce in signed_nonces {
+            let Some(signed) = self.evm_payouts.remove(&(chain_id.0, signed_nonce.0)) else {
+                continue;
+            };
+            self.release_storage(&signed.account_id, storage::entry_bytes(&signed));
+            if signed_nonce.0 < nonce.0 {
+                continue;
+            }
+
+            let SignedEvmPayout { account_id, payout } = signed;
+            require!(
+                self.free_bankroll(&payout.token_id) >= payout.token_amount.0,
+                "free bankroll too low to refund payouts"
+            );
+            *self.bankroll.get_mut(&payout.token_id).unwrap() -= payout.token_amount.0;
+            log!(
+                "evm payout with nonce {} was not mined, added to claimable",
+                signed_nonce.0
+            );
+            self.add_claimable(account_id, payout.token_id, payout.token_amount.0);
+        }
+    }
+
+    /// Cashes out `amount` of claimable winnings to `to` on `chain_id`, converted at the chain's rate.
+    /// Resolves to the signed raw transaction in hex, for anyone to relay.
+    pub fn cash_out_evm(&mut self, chain_id: U64, to: String, amount: U128) -> Promise {
+        self.assert_withdrawals_not_paused();
+        require!(amount.0 > 0, "nothing to cash out");
+        parse_address(&to);
+        let account_id = env::predecessor_account_id();
+        let chain = self.evm_chains.get_mut(&chain_id.0).expect("unknown chain");
+
+        let evm_amount = chain.evm_amount(amount.0);
+        require!(evm_amount > 0, "amount too small to cash out");
+
+        let key = (account_id.clone(), chain.token_id.clone());
+        let claimable = self.claimable.get_mut(&key).expect("nothing to claim");
+        *claimable = claimable
+            .checked_sub(amount.0)
+            .expect("amount above claimable");
+        if *claimable == 0 {
+            self.claimable.remove(&key);
+        }
+
+        // fill the gaps of failed signatures first, later nonces wait on them
+        let nonce = if chain.free_nonces.is_empty() {
+            let nonce = chain.nonce;
+            chain.nonce = U64(nonce.0 + 1);
+            nonce
+        } else {
+            chain.free_nonces.remove(0)
+        };
+        let payout = EvmPayout {
+            token_id: chain.token_id.clone(),
+            token_amount: amount,
+            chain_id,
+            nonce,
+            to,
+            amount: U128(evm_amount),
+            erc20: chain.erc20.clone(),
+            gas_limit: chain.gas_limit,
+            max_fee_per_gas: chain.max_fee_per_gas,
+            max_priority_fee_per_gas: chain.max_priority_fee_per_gas,
+        };
+
+        // kept until a resync once signed
+        let signed = SignedEvmPayout { account_id, payout };
+        self.charge_storage(&signed.account_id, storage::entry_bytes(&signed));
+        let SignedEvmPayout { account_id, payout } = signed;
+
+        self.request_evm_signature(&payout).then(
+            my_contract::ext(env::current_account_id())
+                .with_static_gas(RESOLVE_EVM_PAYOUT_GAS)
+                .resolve_evm_payout(account_id, payout),
+        )
+    }
+
+    /// Signs a stored payout again with the chain's current fees, e.g. when it is stuck
+    /// below the base fee. Only one of the transactions with its nonce can be mined.
+    pub fn resign_evm_payout(&mut self, chain_id: U64, nonce: U64) -> Promise {
+        let chain = self.evm_chains.get(&chain_id.0).expect("unknown chain");
+        let signed = self
+            .evm_payouts
+            .get(&(chain_id.0, nonce.0))
+            .expect("no signed payout");
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            predecessor_id == signed.account_id || predecessor_id == self.owner_id,
+            "only player or owner"
+        );
+
+        let payout = EvmPayout {
+            max_fee_per_gas: chain.max_fee_per_gas,
+            max_priority_fee_per_gas: chain.max_priority_fee_per_gas,
+            ..signed.payout.clone()
+        };
+        self.request_evm_signature(&payout).then(
+            my_contract::ext(env::current_account_id())
+                .with_static_gas(RESOLVE_EVM_PAYOUT_GAS)
+                .resolve_evm_resign(payout),
+        )
+    }
+
+    /// Signed raw transaction of the payout, or `None` when signing failed
+    /// and the amount went back to the player's claimable winnings.
+    /// The house pays a signed payout on the EVM chain, so the tokens stay in the bankroll.
+    #[private]
+    pub fn resolve_evm_payout(
+        &mut self,
+        #[callback_result] call_result: Result<chain_signature::SignResponse, PromiseError>,
+        account_id: AccountId,
+        payout: EvmPayout,
+    ) -> Option<String> {
+        let signed = SignedEvmPayout { account_id, payout };
+        if let Ok(response @ chain_signature::SignResponse::Ecdsa(_)) = call_result {
+            let payout = &signed.payout;
+            let raw_transaction = signed_transaction(payout, response);
+            self.add_bankroll(&payout.token_id, payout.token_amount.0);
+            log!(
+                "evm payout of {} to {} on chain {} signed, nonce {}",
+                payout.amount.0,
+                payout.to,
+                payout.chain_id.0,
+                payout.nonce.0
+            );
+            if let Some(chain) = self.evm_chains.get_mut(&payout.chain_id.0) {
+                let index = chain
+                    .signed_nonces
+                    .binary_search_by_key(&payout.nonce.0, |signed| signed.0)
+                    .unwrap_or_else(|index| index);
+                chain.signed_nonces.insert(index, payout.nonce);
+            }
+            self.evm_payouts
+                .insert((payout.chain_id.0, payout.nonce.0), signed);
+            return Some(format!("0x{}", encode(raw_transaction)));
+        }
+
+        log!("evm payout signature failed, added to claimable");
+        self.release_storage(&signed.account_id, storage::entry_bytes(&signed));
+        let SignedEvmPayout { account_id, payout } = signed;
+        if let Some(chain) = self.evm_chains.get_mut(&payout.chain_id.0) {
+            chain.free_nonce(payout.nonce);
+        }
+        self.add_claimable(account_id, payout.token_id, payout.token_amount.0);
+        None
+    }
+
+    /// Re-signed raw transaction, or `None` when signing failed and the stored payout is kept.
+    #[private]
+    pub fn resolve_evm_resign(
+        &mut self,
+        #[callback_result] call_result: Result<chain_signature::SignResponse, PromiseError>,
+        payout: EvmPayout,
+    ) -> Option<String> {
+        let Ok(response @ chain_signature::SignResponse::Ecdsa(_)) = call_result else {
+            log!("evm payout signature failed");
+            return None;
+        };
+        // resynced meanwhile
+        let signed = self
+            .evm_payouts
+            .get_mut(&(payout.chain_id.0, payout.nonce.0))?;
+
+        let raw_transaction = signed_transaction(&payout, response);
+        log!(
+            "evm payout on chain {} re-signed, nonce {}",
+            payout.chain_id.0,
+            payout.nonce.0
+        );
+        signed.payout = payout;
+        Some(format!("0x{}", encode(raw_transaction)))
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Requests the house signature of the payout transaction hash.
+    fn request_evm_signature(&self, payout: &EvmPayout) -> Promise {
+        let tx_hash = env::keccak256_array(&payout.transaction().build_for_signing());
+        chain_signature::internal_request_signature(
+            self.mpc_contract_id.clone(),
+            chain_signature::derivation_path(EVM_PAYOUT_PURPOSE),
+            encode(tx_hash),
+            chain_signature::SignatureScheme::Ecdsa,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit_storage, setup, sign_requests, signature};
+    use near_sdk::test_utils::accounts;
+
+    const CHAIN_ID: u64 = 11155111;
+    const TO: &str = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+    const AMOUNT: u128 = 1_000_000_000_000_000_000_000_000;
+    // 1 NEAR at 0.001 ETH
+    const RATE: u128 = RATE_SCALE / 1_000;
+    const EVM_AMOUNT: u128 = 1_000_000_000_000_000;
+
+    fn near_chain(erc20: Option<&str>) -> EvmChain {
+        EvmChain {
+            token_id: bankroll::near_token_id(),
+            token_decimals: 24,
+            erc20: erc20.map(str::to_owned),
+            decimals: 18,
+            rate: U128(RATE),
+            nonce: U64(7),
+            free_nonces: vec![],
+            signed_nonces: vec![],
+            gas_limit: U128(21_000),
+            max_fee_per_gas: U128(20_000_000_000),
+            max_priority_fee_per_gas: U128(1_000_000_000),
+        }
+    }
+
+    fn contract_with_chain(erc20: Option<&str>) -> Contract {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        contract.set_evm_chain(U64(CHAIN_ID), near_chain(erc20));
+        contract
+            .claimable
+            .insert((accounts(1), bankroll::near_token_id()), 3 * AMOUNT);
+        deposit_storage(&mut contract, accounts(1));
+        contract
+    }
+
+    fn cash_out(contract: &mut Contract) -> EvmPayout {
+        setup(accounts(1));
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(AMOUNT));
+        cash_out_payout()
+    }
+
+    /// Payout of the first `cash_out`.
+    fn cash_out_payout() -> EvmPayout {
+        EvmPayout {
+            token_id: bankroll::near_token_id(),
+            token_amount: U128(AMOUNT),
+            chain_id: U64(CHAIN_ID),
+            nonce: U64(7),
+            to: TO.to_owned(),
+            amount: U128(EVM_AMOUNT),
+            erc20: None,
+            gas_limit: U128(21_000),
+            max_fee_per_gas: U128(20_000_000_000),
+            max_priority_fee_per_gas: U128(1_000_000_000),
+        }
+    }
+
+    #[test]
+    fn cash_out_signs_transaction_hash() {
+        let mut contract = contract_with_chain(None);
+        let payout = cash_out(&mut contract);
+
+        let near_token_id = bankroll::near_token_id();
+        assert_eq!(
+            contract.get_claimable(accounts(1), near_token_id).0,
+            2 * AMOUNT
+        );
+        assert_eq!(contract.get_evm_chain(U64(CHAIN_ID)).unwrap().nonce.0, 8);
+
+        let requests = sign_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["path"], "alice/evm-payout");
+        assert_eq!(requests[0]["domain_id"], 0);
+        let tx_hash = env::keccak256_array(&payout.transaction().build_for_signing());
+        assert_eq!(requests[0]["payload_v2"]["Ecdsa"], encode(tx_hash));
+    }
+
+    #[test]
+    fn signed_payout_is_raw_eip1559_transaction() {
+        let mut contract = contract_with_chain(None);
+        let payout = cash_out(&mut contract);
+        let near_token_id = bankroll::near_token_id();
+        let bankroll = contract.get_bankroll(near_token_id.clone()).0;
+
+        setup(accounts(0));
+        let raw = contract
+            .resolve_evm_payout(Ok(signature([1; 32])), accounts(1), payout.clone())
+            .unwrap();
+
+        let unsigned = encode(payout.transaction().build_for_signing());
+        assert!(raw.starts_with("0x02"));
+        // same fields, then v 0, r zero and s
+        let fields = &unsigned[4..];
+        assert!(raw.contains(fields));
+        assert!(raw.ends_with(&format!("8080a0{}", "01".repeat(32))));
+        // paid by the house on the EVM chain
+        assert_eq!(contract.get_bankroll(near_token_id).0, bankroll + AMOUNT);
+    }
+
+    #[test]
+    fn failed_signature_restores_claimable_and_nonce() {
+        let mut contract = contract_with_chain(None);
+        let payout = cash_out(&mut contract);
+        let bankroll = contract.get_bankroll(bankroll::near_token_id()).0;
+
+        setup(accounts(0));
+        assert!(contract
+            .resolve_evm_payout(Err(PromiseError::Failed), accounts(1), payout)
+            .is_none());
+
+        assert_eq!(
+            contract
+                .get_claimable(accounts(1), bankroll::near_token_id())
+                .0,
+            3 * AMOUNT
+        );
+        assert_eq!(contract.get_evm_chain(U64(CHAIN_ID)).unwrap().nonce.0, 7);
+        assert_eq!(contract.get_bankroll(bankroll::near_token_id()).0, bankroll);
+    }
+
+    #[test]
+    fn failed_nonce_is_reused_after_later_payouts() {
+        let mut contract = contract_with_chain(None);
+        let first = cash_out(&mut contract);
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(AMOUNT));
+
+        setup(accounts(0));
+        contract.resolve_evm_payout(Err(PromiseError::Failed), accounts(1), first);
+        let chain = contract.get_evm_chain(U64(CHAIN_ID)).unwrap();
+        assert_eq!(chain.nonce.0, 9);
+        assert_eq!(chain.free_nonces, vec![U64(7)]);
+
+        setup(accounts(1));
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(AMOUNT));
+        let chain = contract.get_evm_chain(U64(CHAIN_ID)).unwrap();
+        assert_eq!(chain.nonce.0, 9);
+        assert!(chain.free_nonces.is_empty());
+    }
+
+    /// Cashes out `AMOUNT` and signs it, nonces are 7, 8, ...
+    fn signed_cash_out(contract: &mut Contract) -> EvmPayout {
+        setup(accounts(1));
+        let nonce = contract.get_evm_chain(U64(CHAIN_ID)).unwrap().nonce;
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(AMOUNT));
+        let payout = EvmPayout {
+            nonce,
+            ..cash_out_payout()
+        };
+        setup(accounts(0));
+        contract
+            .resolve_evm_payout(Ok(signature([1; 32])), accounts(1), payout.clone())
+            .unwrap();
+        payout
+    }
+
+    #[test]
+    fn signed_payout_is_kept_until_resync() {
+        let mut contract = contract_with_chain(None);
+        let storage = contract.get_storage_balance(accounts(1)).used_bytes.0;
+        signed_cash_out(&mut contract);
+        signed_cash_out(&mut contract);
+
+        let chain = contract.get_evm_chain(U64(CHAIN_ID)).unwrap();
+        assert_eq!(chain.signed_nonces, vec![U64(7), U64(8)]);
+        let signed = contract.get_evm_payout(U64(CHAIN_ID), U64(7)).unwrap();
+        assert_eq!(signed.account_id, accounts(1));
+        assert!(contract.get_storage_balance(accounts(1)).used_bytes.0 > storage);
+
+        // reconfiguring the chain keeps them
+        contract.set_evm_chain(U64(CHAIN_ID), near_chain(None));
+        let chain = contract.get_evm_chain(U64(CHAIN_ID)).unwrap();
+        assert_eq!(chain.signed_nonces, vec![U64(7), U64(8)]);
+    }
+
+    #[test]
+    fn resync_gives_unmined_payouts_back() {
+        let mut contract = contract_with_chain(None);
+        let near_token_id = bankroll::near_token_id();
+        let storage = contract.get_storage_balance(accounts(1)).used_bytes.0;
+        signed_cash_out(&mut contract);
+        signed_cash_out(&mut contract);
+        let bankroll = contract.get_bankroll(near_token_id.clone()).0;
+
+        // 7 was mined, 8 never was
+        contract.set_evm_nonce(U64(CHAIN_ID), U64(8));
+
+        assert!(contract.get_evm_payout(U64(CHAIN_ID), U64(7)).is_none());
+        assert!(contract.get_evm_payout(U64(CHAIN_ID), U64(8)).is_none());
+        let chain = contract.get_evm_chain(U64(CHAIN_ID)).unwrap();
+        assert!(chain.signed_nonces.is_empty());
+        assert_eq!(chain.nonce.0, 8);
+        assert_eq!(
+            contract.get_claimable(accounts(1), near_token_id.clone()).0,
+            2 * AMOUNT
+        );
+        assert_eq!(contract.get_bankroll(near_token_id).0, bankroll - AMOUNT);
+        assert_eq!(
+            contract.get_storage_balance(accounts(1)).used_bytes.0,
+            storage
+        );
+    }
+
+    #[test]
+    fn resign_uses_current_fees() {
+        let mut contract = contract_with_chain(None);
+        let payout = signed_cash_out(&mut contract);
+        contract.set_evm_gas_price(U64(CHAIN_ID), U128(50_000_000_000), U128(2_000_000_000));
+
+        setup(accounts(1));
+        contract.resign_evm_payout(U64(CHAIN_ID), U64(7));
+
+        let resigned = EvmPayout {
+            max_fee_per_gas: U128(50_000_000_000),
+            max_priority_fee_per_gas: U128(2_000_000_000),
+            ..payout
+        };
+        let tx_hash = env::keccak256_array(&resigned.transaction().build_for_signing());
+        assert_eq!(sign_requests()[0]["payload_v2"]["Ecdsa"], encode(tx_hash));
+
+        setup(accounts(0));
+        let raw = contract
+            .resolve_evm_resign(Ok(signature([1; 32])), resigned.clone())
+            .unwrap();
+        assert!(raw.contains(&encode(resigned.transaction().build_for_signing())[4..]));
+        let signed = contract.get_evm_payout(U64(CHAIN_ID), U64(7)).unwrap();
+        assert_eq!(signed.payout.max_fee_per_gas.0, 50_000_000_000);
+
+        // a failed signature keeps the stored payout
+        assert!(contract
+            .resolve_evm_resign(
+                Err(PromiseError::Failed),
+                EvmPayout {
+                    max_fee_per_gas: U128(1),
+                    ..cash_out_payout()
+                }
+            )
+            .is_none());
+        let signed = contract.get_evm_payout(U64(CHAIN_ID), U64(7)).unwrap();
+        assert_eq!(signed.payout.max_fee_per_gas.0, 50_000_000_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "only player or owner")]
+    fn only_player_or_owner_resigns() {
+        let mut contract = contract_with_chain(None);
+        signed_cash_out(&mut contract);
+
+        setup(accounts(2));
+        contract.resign_evm_payout(U64(CHAIN_ID), U64(7));
+    }
+
+    #[test]
+    fn free_nonces_below_next_nonce_are_not_gaps() {
+        let mut chain = near_chain(None);
+        chain.nonce = U64(10);
+        chain.free_nonce(U64(8));
+        chain.free_nonce(U64(6));
+        assert_eq!(chain.free_nonces, vec![U64(6), U64(8)]);
+
+        chain.free_nonce(U64(9));
+        assert_eq!(chain.nonce.0, 8);
+        assert_eq!(chain.free_nonces, vec![U64(6)]);
+    }
+
+    #[test]
+    fn erc20_payout_calls_transfer() {
+        let erc20 = "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238";
+        let payout = EvmPayout {
+            erc20: Some(erc20.to_owned()),
+            ..cash_out(&mut contract_with_chain(Some(erc20)))
+        };
+
+        let transaction = payout.transaction();
+        assert_eq!(transaction.to, Some(parse_address(erc20)));
+        assert_eq!(transaction.value, 0);
+        assert_eq!(
+            encode(transaction.input),
+            format!(
+                "a9059cbb{:0>64}{:064x}",
+                "d8da6bf26964af9d7eed9e03e53415d37aa96045", EVM_AMOUNT
+            )
+        );
+    }
+
+    #[test]
+    fn evm_amount_converts_decimals_and_rate() {
+        let chain = near_chain(None);
+        assert_eq!(chain.evm_amount(AMOUNT), EVM_AMOUNT);
+        assert_eq!(chain.evm_amount(999_999_999), 0);
+
+        // 6 decimal stablecoin to an 18 decimal one at par
+        let usdc = EvmChain {
+            token_id: "usdc.fakes.testnet".parse().unwrap(),
+            token_decimals: 6,
+            decimals: 18,
+            rate: U128(RATE_SCALE),
+            ..contract_with_chain(None)
+                .evm_chains
+                .remove(&CHAIN_ID)
+                .unwrap()
+        };
+        assert_eq!(usdc.evm_amount(1_500_000), 1_500_000_000_000_000_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "amount too small to cash out")]
+    fn cash_out_below_one_evm_unit() {
+        let mut contract = contract_with_chain(None);
+        setup(accounts(1));
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "amount above claimable")]
+    fn cash_out_above_claimable() {
+        let mut contract = contract_with_chain(None);
+        setup(accounts(1));
+        contract.cash_out_evm(U64(CHAIN_ID), TO.to_owned(), U128(4 * AMOUNT));
+    }
+}
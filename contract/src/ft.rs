@@ -3,8 +3,12 @@ pub const ZERO: u128 = 0;
 
 #[derive(serde::Deserialize, Debug)]
 pub struct SpinFT {
+    #[serde(default)]
     spins: Vec<Vec<roulette::Bet>>,
-    callback_tgas: u8,
+    autoplay: Option<autoplay::Autoplay>, // instead of `spins`
+    callback_tgas: Option<u8>,
+    payout_to: Option<payout::PayoutTo>,
+    referrer_id: Option<AccountId>,
 }
 
 #[allow(dead_code)]
@@ -15,7 +19,7 @@ trait FungibleToken {
 
 #[allow(dead_code)]
 #[ext_contract(ft_receiver)]
-trait FungibleTokenReceiver {
+pub trait FungibleTokenReceiver {
     fn ft_on_transfer(
         &mut self,
         sender_id: AccountId,
@@ -27,7 +31,8 @@ trait FungibleTokenReceiver {
 #[near]
 impl FungibleTokenReceiver for Contract {
     // Callback on receiving tokens by this contract.
-    // `msg` format is either "" for deposit or `TokenReceiverMessage`.
+    // `msg` format is either "" for the owner funding the bankroll or `SpinFT`,
+    // anything else panics and the token refunds the transfer.
     fn ft_on_transfer(
         &mut self,
         sender_id: AccountId,
@@ -36,10 +41,16 @@ impl FungibleTokenReceiver for Contract {
     ) -> PromiseOrValue<U128> {
         let ft_account_id = env::predecessor_account_id();
 
-        let ft_balances = self
-            .balances
-            .get_mut(ft_account_id.as_str())
-            .expect("token not supported");
+        require!(
+            self.balances.contains_key(ft_account_id.as_str()),
+            "token not supported"
+        );
+
+        if msg.is_empty() {
+            require!(sender_id == self.owner_id, "only owner funds the bankroll");
+            self.add_bankroll(&ft_account_id, amount.0);
+            return PromiseOrValue::Value(U128(0));
+        }
 
         let args = serde_json::from_str::<SpinFT>(&msg).expect("WRONG_MSG_FORMAT");
 
@@ -48,12 +59,25 @@ impl FungibleTokenReceiver for Contract {
 
         // ft_balances.insert(sender_id, token_balance);
 
+        let (spins, options) = match args.autoplay {
+            Some(autoplay) => {
+                require!(args.spins.is_empty(), "either spins or autoplay");
+                autoplay.into_spins()
+            }
+            None => (args.spins, SpinOptions::default()),
+        };
+
+        self.register_referrer(&sender_id, args.referrer_id);
         self.spin(
-            args.spins,
+            spins,
             sender_id,
             amount.0,
             ft_account_id,
-            args.callback_tgas,
+            SpinOptions {
+                callback_tgas: args.callback_tgas,
+                payout_to: args.payout_to,
+                ..options
+            },
         );
 
         PromiseOrValue::Value(U128(0))
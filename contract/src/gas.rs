@@ -0,0 +1,181 @@
+use crate::*;
+
+pub const FT_TRANSFER_GAS: Gas = Gas::from_tgas(10);
+
+// rough upper bounds, `callback_tgas` can raise them
+const CALLBACK_BASE_GAS: Gas = Gas::from_tgas(5);
+const CALLBACK_GAS_PER_SPIN: Gas = Gas::from_tgas(1); // history, recent numbers
+const CALLBACK_GAS_PER_BET: Gas = Gas::from_ggas(200); // bet_eval, stats
+const NEAR_PAYOUT_GAS: Gas = Gas::from_tgas(2);
+// gas `spin` itself still uses after checking
+const SPIN_GAS_RESERVE: Gas = Gas::from_tgas(5);
+
+/// Gas `mpc_callback` needs to settle `spins` and pay out in `token_id`.
+pub fn callback_gas_for(
+    spins: &[Vec<roulette::Bet>],
+    token_id: &AccountId,
+    options: &SpinOptions,
+) -> Gas {
+    let mut payouts = options
+        .payout_to
+        .as_ref()
+        .map_or(1, |payout_to| payout_to.recipient_count()) as u64;
+    // refund of spins not played
+    if options.stop.is_some() {
+        payouts += 1;
+    }
+    let bets = spins.iter().map(|bets| bets.len() as u64).sum::<u64>();
+    let payout_gas = match token_id.as_str() {
+        "near" => NEAR_PAYOUT_GAS,
+        _ => FT_TRANSFER_GAS.saturating_add(NEAR_PAYOUT_GAS),
+    }
+    .saturating_add(claim::RESOLVE_PAYOUT_GAS);
+
+    CALLBACK_BASE_GAS
+        .saturating_add(CALLBACK_GAS_PER_SPIN.saturating_mul(spins.len() as u64))
+        .saturating_add(CALLBACK_GAS_PER_BET.saturating_mul(bets))
+        .saturating_add(payout_gas.saturating_mul(payouts))
+}
+
+/// Estimate, raised to `options.callback_tgas` when given. The estimate is the floor.
+pub fn callback_gas(
+    spins: &[Vec<roulette::Bet>],
+    token_id: &AccountId,
+    options: &SpinOptions,
+) -> Gas {
+    let estimate = callback_gas_for(spins, token_id, options);
+    match options.callback_tgas {
+        Some(callback_tgas) => estimate.max(Gas::from_tgas(callback_tgas as u64)),
+        None => estimate,
+    }
+}
+
+pub fn assert_enough_gas(callback_gas: Gas) {
+    let required_gas = chain_signature::SIGN_GAS
+        .saturating_add(callback_gas)
+        .saturating_add(SPIN_GAS_RESERVE);
+    let remaining_gas = env::prepaid_gas().saturating_sub(env::used_gas());
+    require!(
+        remaining_gas >= required_gas,
+        format!(
+            "not enough gas, attach at least {} more",
+            required_gas.saturating_sub(remaining_gas)
+        )
+    );
+}
+
+#[near]
+impl Contract {
+    pub fn estimate_callback_gas(
+        &self,
+        spins: Vec<Vec<roulette::Bet>>,
+        token_id: AccountId,
+        payout_to: Option<payout::PayoutTo>,
+        stop: Option<autoplay::StopConditions>,
+    ) -> Gas {
+        let options = SpinOptions {
+            callback_tgas: None,
+            payout_to,
+            stop,
+            strategy: None,
+        };
+        callback_gas_for(&spins, &token_id, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::context;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    /// Player calls with `tgas` attached.
+    fn setup_with_gas(tgas: u64) {
+        testing_env!(context(accounts(1))
+            .prepaid_gas(Gas::from_tgas(tgas))
+            .build());
+    }
+
+    fn spins(spin_count: usize) -> Vec<Vec<roulette::Bet>> {
+        let bet = roulette::Bet {
+            kind: roulette::BetKind::Red,
+            amount: NearToken::from_yoctonear(1),
+            number: 0,
+        };
+        vec![vec![bet; 13]; spin_count]
+    }
+
+    #[test]
+    fn callback_tgas_only_raises_estimate() {
+        let near_token_id = bankroll::near_token_id();
+        let options = |callback_tgas| SpinOptions {
+            callback_tgas,
+            ..Default::default()
+        };
+        let estimate = callback_gas_for(&spins(4), &near_token_id, &options(None));
+
+        assert_eq!(
+            callback_gas(&spins(4), &near_token_id, &options(Some(3))),
+            estimate
+        );
+        assert_eq!(
+            callback_gas(&spins(4), &near_token_id, &options(Some(200))),
+            Gas::from_tgas(200)
+        );
+        assert!(
+            callback_gas_for(
+                &spins(4),
+                &"usdc.fakes.testnet".parse().unwrap(),
+                &SpinOptions::default()
+            ) > estimate
+        );
+        assert!(callback_gas_for(&spins(8), &near_token_id, &options(None)) > estimate);
+
+        let autoplay = SpinOptions {
+            stop: Some(autoplay::StopConditions {
+                stop_loss: None,
+                take_profit: None,
+            }),
+            ..Default::default()
+        };
+        assert!(callback_gas_for(&spins(4), &near_token_id, &autoplay) > estimate);
+    }
+
+    #[test]
+    fn max_ft_recipients_fit_in_a_transaction() {
+        setup_with_gas(300);
+        let recipients = (0..payout::MAX_RECIPIENTS)
+            .map(|_| payout::Recipient {
+                account_id: "bob.near".parse().unwrap(),
+                weight: 1,
+            })
+            .collect();
+        let options = SpinOptions {
+            payout_to: Some(payout::PayoutTo::Split(recipients)),
+            stop: Some(autoplay::StopConditions {
+                stop_loss: None,
+                take_profit: None,
+            }),
+            ..Default::default()
+        };
+
+        assert_enough_gas(callback_gas_for(
+            &spins(20),
+            &"usdc.fakes.testnet".parse().unwrap(),
+            &options,
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "not enough gas")]
+    fn low_prepaid_gas_is_rejected() {
+        setup_with_gas(20);
+
+        assert_enough_gas(callback_gas_for(
+            &spins(4),
+            &bankroll::near_token_id(),
+            &SpinOptions::default(),
+        ));
+    }
+}
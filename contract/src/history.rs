@@ -0,0 +1,132 @@
+use crate::*;
+
+pub const DEFAULT_HISTORY_LIMIT: u32 = 100;
+pub const RECENT_NUMBERS_LEN: usize = 100;
+const MAX_PAGE_LIMIT: u32 = 100;
+
+/// One settled wheel spin, stored per player.
+#[derive(Clone, Debug)]
+#[near(serializers = [json, borsh])]
+pub struct SpinRecord {
+    pub spin_id: U64,
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub block_height: U64,
+    pub bets: Vec<roulette::Bet>,
+    pub number: u8, // pocket the ball landed in
+    pub payout: U128,
+    pub scheme: chain_signature::SignatureScheme, // of the signature the number came from
+}
+
+impl SpinRecord {
+    /// Storage of the record and its id in the player's history.
+    pub fn storage_bytes(&self) -> u64 {
+        storage::entry_bytes(self) + 8
+    }
+}
+
+#[near(serializers = [json])]
+pub struct RecentNumber {
+    pub number: u8,
+    pub color: roulette::Color,
+}
+
+#[near]
+impl Contract {
+    /// Last numbers across all players, newest first.
+    pub fn get_recent_numbers(&self, limit: Option<u32>) -> Vec<RecentNumber> {
+        let limit = limit.map_or(RECENT_NUMBERS_LEN, |limit| limit as usize);
+        self.recent_numbers
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|number| RecentNumber {
+                number: *number,
+                color: roulette::number_color(*number),
+            })
+            .collect()
+    }
+
+    /// Hits per pocket, indexed by number 0..=36.
+    pub fn get_pocket_hits(&self) -> Vec<U64> {
+        self.pocket_hits.iter().map(|hits| U64(*hits)).collect()
+    }
+
+    /// Spins of `account_id`, oldest first.
+    pub fn get_player_history(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<SpinRecord> {
+        let Some(spin_ids) = self.history.get(&account_id) else {
+            return vec![];
+        };
+
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(MAX_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+
+        spin_ids
+            .iter()
+            .skip(from_index)
+            .take(limit)
+            .filter_map(|spin_id| self.spin_records.get(spin_id).cloned())
+            .collect()
+    }
+
+    pub fn get_player_history_len(&self, account_id: AccountId) -> u32 {
+        self.history
+            .get(&account_id)
+            .map_or(0, |spin_ids| spin_ids.len() as u32)
+    }
+
+    pub fn get_spin(&self, spin_id: U64) -> Option<SpinRecord> {
+        self.spin_records.get(&spin_id.0).cloned()
+    }
+
+    pub fn get_history_limit(&self) -> Option<u32> {
+        self.history_limit
+    }
+
+    /// `None` keeps every spin, otherwise only the last `limit` spins per player are kept.
+    pub fn set_history_limit(&mut self, limit: Option<u32>) {
+        self.assert_owner();
+        self.history_limit = limit;
+    }
+}
+
+// internal
+
+impl Contract {
+    pub(crate) fn record_number(&mut self, number: u8) {
+        self.pocket_hits[number as usize] += 1;
+
+        if self.recent_numbers.len() == RECENT_NUMBERS_LEN {
+            self.recent_numbers.pop_front();
+        }
+        self.recent_numbers.push_back(number);
+    }
+
+    /// Stores the record on the player's storage deposit, dropped records free theirs.
+    pub(crate) fn record_spin(&mut self, record: SpinRecord) {
+        let spin_id = record.spin_id.0;
+        let account_id = record.account_id.clone();
+        self.charge_storage(&account_id, record.storage_bytes());
+        self.spin_records.insert(spin_id, record);
+
+        let spin_ids = self.history.entry(account_id.clone()).or_default();
+        spin_ids.push(spin_id);
+
+        // drop the oldest spins over the limit
+        let mut freed_bytes = 0;
+        if let Some(limit) = self.history_limit {
+            let excess = spin_ids.len().saturating_sub(limit as usize);
+            for old_spin_id in spin_ids.drain(..excess) {
+                if let Some(old) = self.spin_records.remove(&old_spin_id) {
+                    freed_bytes += old.storage_bytes();
+                }
+            }
+        }
+        self.release_storage(&account_id, freed_bytes);
+    }
+}
@@ -2,18 +2,38 @@ use std::str::FromStr;
 
 use hex::encode;
 use near_sdk::{
+    borsh::BorshDeserialize,
     env::{self},
     ext_contract,
-    json_types::U128,
+    json_types::{I128, U128, U64},
     log, near, require, serde, serde_json,
-    store::LookupMap,
+    store::{IterableSet, LookupMap, LookupSet},
     AccountId, Gas, NearToken, Promise, PromiseError, PromiseOrValue,
 };
-use omni_transaction::signer::types::SignatureResponse;
+use std::collections::VecDeque;
 
+mod autoplay;
+mod bankroll;
 mod chain_signature;
+mod claim;
+mod evm;
 mod ft;
+mod gas;
+mod history;
+mod limits;
+mod loyalty;
+mod migrate;
+mod owner;
+mod pause;
+mod payout;
+mod pending;
+mod referral;
 pub mod roulette;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod tests;
+mod upgrade;
 
 // TODO make enum for inside/outside/call bet types
 // see notes/european-roulette-bets.txt
@@ -21,36 +41,124 @@ pub mod roulette;
 #[allow(dead_code)]
 #[ext_contract(my_contract)]
 trait MyContract {
-    fn mpc_callback(
-        &mut self,
-        sender_id: AccountId,
-        spins: Vec<Vec<roulette::Bet>>,
-        token_id: AccountId,
-    );
+    fn mpc_callback(&mut self, spin_id: u64);
+    fn resolve_payout(&mut self, receiver_id: AccountId, token_id: AccountId, amount: U128);
+    fn resolve_withdraw_bankroll(&mut self, token_id: AccountId, amount: U128);
+    fn resolve_evm_payout(&mut self, account_id: AccountId, payout: evm::EvmPayout);
+    fn resolve_evm_resign(&mut self, payout: evm::EvmPayout);
 }
 
 #[near(contract_state)]
 pub struct Contract {
+    owner_id: AccountId,
     spins: u128,
     bets: u128,
-    house: u128,
-    payout: u128,
+    // refunded spins leave `spins`, ids are never reused
+    next_spin_id: u64,
+    // hits per pocket number, index 0..=36
+    pocket_hits: Vec<u64>,
+    // last numbers, oldest first
+    recent_numbers: VecDeque<u8>,
     // fts
     balances: LookupMap<String, LookupMap<AccountId, u128>>,
+    // spin history
+    history: LookupMap<AccountId, Vec<u64>>,
+    spin_records: LookupMap<u64, history::SpinRecord>,
+    history_limit: Option<u32>,
+    // per token stats
+    token_stats: LookupMap<AccountId, stats::TokenStats>,
+    // per token house funds and max payout owed to spins in flight
+    bankroll: LookupMap<AccountId, u128>,
+    liabilities: LookupMap<AccountId, u128>,
+    // self upgrade
+    staged_upgrade: Option<upgrade::StagedUpgrade>,
+    upgrade_delay_ns: u64,
+    lowered_upgrade_delay: Option<upgrade::LoweredDelay>,
+    upgrade_approver_id: Option<AccountId>,
+    // emergency stop
+    guardians: IterableSet<AccountId>,
+    paused: pause::Paused,
+    // spins waiting on their signature, by first spin id
+    pending_spins: LookupMap<u64, pending::PendingSpin>,
+    pending_timeout_blocks: u64,
+    // failed payouts by (player, token)
+    claimable: LookupMap<(AccountId, AccountId), u128>,
+    // players whose winnings are credited to claimable, e.g. to cash out on EVM
+    holding_winnings: LookupSet<AccountId>,
+    // referrals, a referrer can only be set before the player's first bet
+    bettors: LookupSet<AccountId>,
+    referrers: LookupMap<AccountId, AccountId>,
+    referred_count: LookupMap<AccountId, u32>,
+    referral_stats: LookupMap<(AccountId, AccountId), referral::ReferralStats>,
+    referral_share_bps: u16,
+    referral_profit: LookupMap<(AccountId, AccountId), referral::HouseProfit>,
+    // rakeback by (player, token)
+    loyalty: LookupMap<(AccountId, AccountId), loyalty::Loyalty>,
+    rakeback_tiers: LookupMap<AccountId, Vec<loyalty::RakebackTier>>,
+    // responsible gaming, limits by (player, token)
+    limits: LookupMap<(AccountId, AccountId), limits::PlayerLimits>,
+    exclusions: LookupMap<AccountId, u64>,
+    // signer of the spin randomness
+    mpc_contract_id: AccountId,
+    staged_mpc_contract_id: Option<chain_signature::StagedSigner>,
+    signature_scheme: chain_signature::SignatureScheme,
+    // cash out to EVM chains, by chain id
+    evm_chains: LookupMap<u64, evm::EvmChain>,
+    evm_payouts: LookupMap<(u64, u64), evm::SignedEvmPayout>,
+    // NEAR deposited by players for the storage of their spins
+    storage_balances: LookupMap<AccountId, storage::StorageBalance>,
 }
 
 impl Default for Contract {
     fn default() -> Self {
         let mut this = Self {
+            owner_id: env::current_account_id(),
             spins: 0,
             bets: 0,
-            house: 100_000_000_000_000_000_000_000_000,
-            payout: 0,
+            next_spin_id: 0,
+            pocket_hits: vec![0; 37],
+            recent_numbers: VecDeque::with_capacity(history::RECENT_NUMBERS_LEN),
             balances: LookupMap::new(b"a"),
+            history: LookupMap::new(b"c"),
+            spin_records: LookupMap::new(b"d"),
+            history_limit: Some(history::DEFAULT_HISTORY_LIMIT),
+            token_stats: LookupMap::new(b"e"),
+            bankroll: LookupMap::new(b"f"),
+            liabilities: LookupMap::new(b"g"),
+            staged_upgrade: None,
+            upgrade_delay_ns: upgrade::DEFAULT_UPGRADE_DELAY_NS,
+            lowered_upgrade_delay: None,
+            upgrade_approver_id: None,
+            guardians: IterableSet::new(b"h"),
+            paused: pause::Paused::default(),
+            pending_spins: LookupMap::new(b"i"),
+            pending_timeout_blocks: pending::DEFAULT_PENDING_TIMEOUT_BLOCKS,
+            claimable: LookupMap::new(b"j"),
+            holding_winnings: LookupSet::new(b"t"),
+            bettors: LookupSet::new(b"s"),
+            referrers: LookupMap::new(b"k"),
+            referred_count: LookupMap::new(b"l"),
+            referral_stats: LookupMap::new(b"m"),
+            referral_share_bps: referral::DEFAULT_REFERRAL_SHARE_BPS,
+            referral_profit: LookupMap::new(b"v"),
+            loyalty: LookupMap::new(b"n"),
+            rakeback_tiers: LookupMap::new(b"o"),
+            limits: LookupMap::new(b"p"),
+            exclusions: LookupMap::new(b"q"),
+            mpc_contract_id: chain_signature::default_mpc_contract_id(),
+            staged_mpc_contract_id: None,
+            signature_scheme: chain_signature::SignatureScheme::Ecdsa,
+            evm_chains: LookupMap::new(b"r"),
+            evm_payouts: LookupMap::new(b"w"),
+            storage_balances: LookupMap::new(b"u"),
         };
 
         this.balances
             .set("usdc.fakes.testnet".to_owned(), Some(LookupMap::new(b"b")));
+        this.bankroll.set(
+            bankroll::near_token_id(),
+            Some(100_000_000_000_000_000_000_000_000),
+        );
 
         this
     }
@@ -58,86 +166,166 @@ impl Default for Contract {
 
 #[near]
 impl Contract {
+    /// (spins, bets, NEAR bankroll, NEAR paid), see `stats_v2` for other tokens
     pub fn stats(&self) -> (U128, U128, U128, U128) {
+        let near_token_id = bankroll::near_token_id();
         (
             U128(self.spins),
             U128(self.bets),
-            U128(self.house),
-            U128(self.payout),
+            self.get_bankroll(near_token_id.clone()),
+            self.stats_v2(near_token_id).paid,
         )
     }
 
+    /// `callback_tgas` can raise the estimated settlement gas, see `estimate_callback_gas`.
+    /// Winnings go to `payout_to` when set, refunds always go to the player.
+    /// `referrer_id` is recorded on the player's first bet.
     #[payable]
-    pub fn spin_with_near(&mut self, spins: Vec<Vec<roulette::Bet>>, callback_tgas: u8) -> Promise {
+    pub fn spin_with_near(
+        &mut self,
+        spins: Vec<Vec<roulette::Bet>>,
+        callback_tgas: Option<u8>,
+        payout_to: Option<payout::PayoutTo>,
+        referrer_id: Option<AccountId>,
+    ) -> Promise {
         let amount = env::attached_deposit();
         let sender_id = env::predecessor_account_id();
+        self.register_referrer(&sender_id, referrer_id);
         self.spin(
             spins,
             sender_id,
             amount.as_yoctonear(),
-            AccountId::from_str("near").unwrap(),
-            callback_tgas,
+            bankroll::near_token_id(),
+            SpinOptions {
+                callback_tgas,
+                payout_to,
+                ..Default::default()
+            },
         )
     }
 
     #[private]
     pub fn mpc_callback(
         &mut self,
-        #[callback_result] call_result: Result<SignatureResponse, PromiseError>,
-        sender_id: AccountId,
-        spins: Vec<Vec<roulette::Bet>>,
-        token_id: AccountId, // payout token
+        #[callback_result] call_result: Result<chain_signature::SignResponse, PromiseError>,
+        spin_id: u64, // id of the first spin of the pending spin
     ) -> Vec<Vec<(bool, u8, bool, u8)>> {
         let mut results: Vec<Vec<(bool, u8, bool, u8)>> = vec![];
+        let Some(pending) = self.pending_spins.remove(&spin_id) else {
+            log!("spin {} already reclaimed", spin_id);
+            return results;
+        };
+
         match call_result {
-            Ok(signature_response) => {
-                // get bytes from signature
-                let mut r_bytes =
-                    hex::decode(signature_response.big_r.affine_point).expect("r_bytes failed");
-                // first r_byte is compression flag
-                r_bytes.remove(0);
-                // all byte of s_bytes should be random for signature s is scalar value
-                let mut s_bytes = hex::decode(signature_response.s.scalar).expect("s_bytes failed");
-                s_bytes.extend(r_bytes);
+            Ok(sign_response) => {
+                let pending::PendingSpin {
+                    account_id: sender_id,
+                    token_id, // payout token
+                    spins,
+                    liability,
+                    payout_to,
+                    stop,
+                    strategy,
+                    storage_bytes,
+                    ..
+                } = pending;
+                // the records below take the storage held for them
+                self.release_storage(&sender_id, storage_bytes.0);
+
+                let scheme = sign_response.scheme();
+                let s_bytes = sign_response.entropy();
 
                 let mut payout: u128 = 0;
+                let mut wagered: u128 = 0; // by spins played
+                let mut refund: u128 = 0; // of spins not played after a stop condition
+                let mut unplayed = vec![];
+                let mut records = vec![];
+                // progression from the base bets, the first spin's
+                let progression = strategy.map(|strategy| (strategy, spins[0].clone()));
+                let mut step = 0;
+                let token_stats = self
+                    .token_stats
+                    .entry(token_id.clone())
+                    .or_insert_with(stats::TokenStats::new);
+
+                for (i, mut bets) in spins.into_iter().enumerate() {
+                    let mut spin_wager: u128 =
+                        bets.iter().map(|bet| bet.amount.as_yoctonear()).sum();
+                    if stop
+                        .as_ref()
+                        .is_some_and(|stop| stop.reached(wagered, payout))
+                    {
+                        refund += spin_wager;
+                        unplayed.push(bets);
+                        continue;
+                    }
+                    if let Some((strategy, base_bets)) = &progression {
+                        let staked = strategy.scale(base_bets, step);
+                        let staked_wager: u128 =
+                            staked.iter().map(|bet| bet.amount.as_yoctonear()).sum();
+                        refund += spin_wager - staked_wager;
+                        token_stats.remove_spin(&bets);
+                        token_stats.add_spin(&staked);
+                        bets = staked;
+                        spin_wager = staked_wager;
+                    }
+                    wagered += spin_wager;
 
-                for (i, bets) in spins.iter().enumerate() {
                     let mut spin_result = vec![];
-                    for bet in bets {
+                    let mut spin_payout: u128 = 0;
+                    let spin_number = roulette::pocket(s_bytes[i]);
+                    for bet in &bets {
                         let (win, number, red, multiple) = roulette::bet_eval(s_bytes[i], bet);
 
                         let amount = bet.amount.as_yoctonear();
                         if multiple > 0 {
-                            payout = payout
-                                .checked_add(
-                                    amount
-                                        .checked_mul((multiple + 1) as u128)
-                                        .expect("payout overflow"),
-                                )
+                            let bet_payout = amount
+                                .checked_mul((multiple + 1) as u128)
                                 .expect("payout overflow");
+                            spin_payout = spin_payout
+                                .checked_add(bet_payout)
+                                .expect("payout overflow");
+                            token_stats.add_payout(bet.kind, bet_payout);
                         }
 
                         spin_result.push((win, number, red, multiple));
                     }
+                    payout = payout.checked_add(spin_payout).expect("payout overflow");
                     results.push(spin_result);
+                    if let Some((strategy, _)) = &progression {
+                        step = strategy.next_step(step, spin_payout > spin_wager);
+                    }
+
+                    records.push(history::SpinRecord {
+                        spin_id: U64(spin_id + i as u64),
+                        account_id: sender_id.clone(),
+                        token_id: token_id.clone(),
+                        block_height: U64(env::block_height()),
+                        bets,
+                        number: spin_number,
+                        payout: U128(spin_payout),
+                        scheme,
+                    });
                 }
 
-                self.house = self.house.checked_sub(payout).expect("house empty");
-                self.payout = self.payout.checked_add(payout).expect("paid overflow");
+                self.remove_spins(&token_id, &unplayed);
+                for record in records {
+                    self.record_number(record.number);
+                    self.record_spin(record);
+                }
 
-                match token_id.as_str() {
-                    "near" => Promise::new(sender_id).transfer(NearToken::from_yoctonear(payout)),
-                    _ => ft::ft_contract::ext(token_id)
-                        .with_static_gas(Gas::from_tgas(50))
-                        .with_attached_deposit(NearToken::from_yoctonear(1))
-                        .ft_transfer(sender_id, U128(payout), None),
-                };
+                self.settle_liability(&token_id, liability.0, payout + refund);
+                self.accrue_referral(&sender_id, &token_id, wagered, payout);
+                self.accrue_loyalty(&sender_id, &token_id, wagered);
+                self.record_limits_payout(&sender_id, &token_id, payout + refund);
+                self.pay_winnings(token_id.clone(), sender_id.clone(), payout_to, payout);
+                self.pay_out(token_id, sender_id, refund);
 
                 results
             }
             Err(error) => {
                 env::log_str(&format!("mpc callback failed with error: {:?}", error));
+                self.refund_pending(pending);
                 results.push(vec![(false, 0, false, 0)]);
                 results
             }
@@ -147,6 +335,15 @@ impl Contract {
 
 // internal
 
+/// Player options of a spin request.
+#[derive(Default)]
+pub struct SpinOptions {
+    pub callback_tgas: Option<u8>,
+    pub payout_to: Option<payout::PayoutTo>,
+    pub stop: Option<autoplay::StopConditions>,
+    pub strategy: Option<roulette::Strategy>,
+}
+
 impl Contract {
     pub fn spin(
         &mut self,
@@ -154,46 +351,107 @@ impl Contract {
         sender_id: AccountId,
         amount: u128,
         token_id: AccountId,
-        callback_tgas: u8,
+        options: SpinOptions,
     ) -> Promise {
         require!(spins.len() < 64, "too many spins");
+        self.assert_spins_not_paused(&token_id);
+        if let Some(payout_to) = &options.payout_to {
+            payout_to.assert_valid();
+        }
+
+        let callback_gas = gas::callback_gas(&spins, &token_id, &options);
+        gas::assert_enough_gas(callback_gas);
+
+        let spin_id = self.next_spin_id;
+        let token_stats = self
+            .token_stats
+            .entry(token_id.clone())
+            .or_insert_with(stats::TokenStats::new);
 
         // required_amount is all bet amounts
         let mut required_amount: u128 = 0;
+        let mut liability: u128 = 0;
         for bets in &spins {
             for bet in bets {
+                // is bet legal
+                require!(roulette::bet_legal(bet), "illegal bet");
+                require!(bet.amount.as_yoctonear() > 0, "zero bet");
+
                 required_amount = required_amount
                     .checked_add(bet.amount.as_yoctonear())
                     .expect("bet.amount overflow");
-
-                // is bet legal
-                require!(roulette::bet_legal(&bet), "illegal bet");
-
-                self.bets += 1;
             }
+            liability = liability
+                .checked_add(roulette::max_payout(bets))
+                .expect("liability overflow");
+
+            token_stats.add_spin(bets);
+            self.bets += bets.len() as u128;
             self.spins += 1;
+            self.next_spin_id += 1;
         }
 
         require!(amount == required_amount, "deposit != bet amount");
+        self.check_limits(&sender_id, &token_id, required_amount);
+        // the bettors entry and the history list of a first bet are kept
+        if self.bettors.insert(sender_id.clone()) {
+            let first_bet_bytes = storage::entry_bytes(&sender_id) + storage::STORAGE_ENTRY_BYTES;
+            self.charge_storage(&sender_id, first_bet_bytes);
+        }
+        // records of every spin, held until settled
+        let mut storage_bytes = 0;
+        for bets in &spins {
+            storage_bytes += history::SpinRecord {
+                spin_id: U64(spin_id),
+                account_id: sender_id.clone(),
+                token_id: token_id.clone(),
+                block_height: U64(env::block_height()),
+                bets: bets.clone(),
+                number: 0,
+                payout: U128(0),
+                scheme: self.signature_scheme,
+            }
+            .storage_bytes();
+        }
 
-        self.house = self
-            .house
-            .checked_add(required_amount)
-            .expect("house overflow");
+        self.lock_liability(&token_id, required_amount, liability);
 
-        // get chain signature
-        let account_id = env::predecessor_account_id();
-        let random_seed = env::random_seed_array();
+        // signature payload bound to this spin and its bets
+        let payload = chain_signature::spin_payload(
+            spin_id,
+            &sender_id,
+            &spins,
+            env::block_height(),
+            env::random_seed_array(),
+        );
 
+        let mut pending = pending::PendingSpin {
+            account_id: sender_id.clone(),
+            token_id,
+            spins,
+            amount: U128(required_amount),
+            liability: U128(liability),
+            block_height: U64(env::block_height()),
+            payout_to: options.payout_to,
+            stop: options.stop,
+            strategy: options.strategy,
+            storage_bytes: U64(0),
+        };
+        pending.storage_bytes = U64(storage_bytes + storage::entry_bytes(&pending));
+        self.charge_storage(&sender_id, pending.storage_bytes.0);
+        self.pending_spins.insert(spin_id, pending);
+
+        // get chain signature
         chain_signature::internal_request_signature(
-            account_id.to_string(),
-            encode(random_seed),
-            "Ecdsa".to_owned(),
+            self.mpc_contract_id.clone(),
+            chain_signature::derivation_path(chain_signature::SPIN_PURPOSE),
+            encode(payload),
+            self.signature_scheme,
         )
         .then(
             my_contract::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(callback_tgas as u64))
-                .mpc_callback(sender_id, spins, token_id),
+                .with_static_gas(callback_gas)
+                .mpc_callback(spin_id),
         )
     }
 }
@@ -0,0 +1,431 @@
+use crate::*;
+
+const HOUR_NS: u64 = 60 * 60 * 1_000_000_000;
+const DAY_NS: u64 = 24 * HOUR_NS;
+// loosened limits apply after this delay, tightened ones at once
+pub const LOOSEN_DELAY_NS: u64 = DAY_NS;
+const DAY_HOURS: u64 = 24;
+const WEEK_DAYS: u64 = 7;
+const MONTH_DAYS: u64 = 30;
+
+/// Limits of one player for one token, `None` is no limit.
+/// Windows are rolling: the last 24 hours counted in whole hours, the last 7 and
+/// 30 days in whole days plus the current one.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[near(serializers = [json, borsh])]
+pub struct Limits {
+    pub daily_wager: Option<U128>,
+    pub weekly_wager: Option<U128>,
+    pub monthly_wager: Option<U128>,
+    pub daily_loss: Option<U128>,
+    pub weekly_loss: Option<U128>,
+    pub monthly_loss: Option<U128>,
+}
+
+fn tighter(a: Option<U128>, b: Option<U128>) -> Option<U128> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(U128(a.0.min(b.0))),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl Limits {
+    /// The tighter of each limit.
+    pub fn tightest(&self, other: &Limits) -> Limits {
+        Limits {
+            daily_wager: tighter(self.daily_wager, other.daily_wager),
+            weekly_wager: tighter(self.weekly_wager, other.weekly_wager),
+            monthly_wager: tighter(self.monthly_wager, other.monthly_wager),
+            daily_loss: tighter(self.daily_loss, other.daily_loss),
+            weekly_loss: tighter(self.weekly_loss, other.weekly_loss),
+            monthly_loss: tighter(self.monthly_loss, other.monthly_loss),
+        }
+    }
+
+    // (window, wager limit, loss limit), the current day counts in full
+    fn windows(&self) -> [(Window, Option<U128>, Option<U128>); 3] {
+        [
+            (Window::Hours(DAY_HOURS), self.daily_wager, self.daily_loss),
+            (
+                Window::Days(WEEK_DAYS + 1),
+                self.weekly_wager,
+                self.weekly_loss,
+            ),
+            (
+                Window::Days(MONTH_DAYS + 1),
+                self.monthly_wager,
+                self.monthly_loss,
+            ),
+        ]
+    }
+}
+
+/// Last periods a window sums, the current one included.
+#[derive(Clone, Copy)]
+enum Window {
+    Hours(u64),
+    Days(u64),
+}
+
+/// Play in one hour or day.
+#[near(serializers = [borsh])]
+pub struct PeriodTotals {
+    pub period: u64, // hours or days since the epoch
+    pub wagered: u128,
+    pub paid: u128,
+}
+
+#[near(serializers = [borsh])]
+pub struct PlayerLimits {
+    pub active: Limits,
+    pub pending: Option<(Limits, u64)>, // loosened limits, effective at block timestamp
+    // play since limits were set, the last 24 hours and 31 days
+    pub hours: Vec<PeriodTotals>,
+    pub days: Vec<PeriodTotals>,
+}
+
+fn sum(periods: &[PeriodTotals], len: u64, period: u64) -> (u128, u128) {
+    periods
+        .iter()
+        .filter(|totals| totals.period + len > period)
+        .fold((0, 0), |(wagered, paid), totals| {
+            (wagered + totals.wagered, paid + totals.paid)
+        })
+}
+
+fn totals(periods: &mut Vec<PeriodTotals>, period: u64) -> &mut PeriodTotals {
+    if periods.last().is_none_or(|totals| totals.period != period) {
+        periods.push(PeriodTotals {
+            period,
+            wagered: 0,
+            paid: 0,
+        });
+    }
+    periods.last_mut().unwrap()
+}
+
+impl PlayerLimits {
+    /// Applies pending limits that are due and drops periods outside every window.
+    pub fn refresh(&mut self, now: u64) {
+        if let Some((_, effective_at)) = &self.pending {
+            if now >= *effective_at {
+                self.active = self.pending.take().unwrap().0;
+            }
+        }
+
+        let (hour, day) = (now / HOUR_NS, now / DAY_NS);
+        self.hours.retain(|totals| totals.period + DAY_HOURS > hour);
+        self.days
+            .retain(|totals| totals.period + MONTH_DAYS + 1 > day);
+    }
+
+    /// (wagered, paid) over `window` at block timestamp `now`.
+    fn window(&self, window: Window, now: u64) -> (u128, u128) {
+        match window {
+            Window::Hours(hours) => sum(&self.hours, hours, now / HOUR_NS),
+            Window::Days(days) => sum(&self.days, days, now / DAY_NS),
+        }
+    }
+
+    /// Wagering `wager` must keep every window within its limits, counting it as lost.
+    pub fn assert_allows(&self, wager: u128, now: u64) {
+        for (window, wager_limit, loss_limit) in self.active.windows() {
+            let (wagered, paid) = self.window(window, now);
+            if let Some(wager_limit) = wager_limit {
+                require!(wagered + wager <= wager_limit.0, "wager limit reached");
+            }
+            if let Some(loss_limit) = loss_limit {
+                require!(
+                    wagered.saturating_sub(paid) + wager <= loss_limit.0,
+                    "loss limit reached"
+                );
+            }
+        }
+    }
+
+    fn record(&mut self, now: u64, wagered: u128, paid: u128) {
+        for totals in [
+            totals(&mut self.hours, now / HOUR_NS),
+            totals(&mut self.days, now / DAY_NS),
+        ] {
+            totals.wagered += wagered;
+            totals.paid += paid;
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct LimitsView {
+    pub active: Limits,
+    pub pending: Option<Limits>,
+    pub pending_effective_at: Option<U64>,
+}
+
+#[near]
+impl Contract {
+    pub fn get_limits(&self, account_id: AccountId, token_id: AccountId) -> Option<LimitsView> {
+        self.limits
+            .get(&(account_id, token_id))
+            .map(|player_limits| {
+                let now = env::block_timestamp();
+                match &player_limits.pending {
+                    Some((pending, effective_at)) if now >= *effective_at => LimitsView {
+                        active: pending.clone(),
+                        pending: None,
+                        pending_effective_at: None,
+                    },
+                    pending => LimitsView {
+                        active: player_limits.active.clone(),
+                        pending: pending.as_ref().map(|(limits, _)| limits.clone()),
+                        pending_effective_at: pending
+                            .as_ref()
+                            .map(|(_, effective_at)| U64(*effective_at)),
+                    },
+                }
+            })
+    }
+
+    pub fn get_excluded_until(&self, account_id: AccountId) -> Option<U64> {
+        self.exclusions
+            .get(&account_id)
+            .filter(|excluded_until| **excluded_until > env::block_timestamp())
+            .map(|excluded_until| U64(*excluded_until))
+    }
+
+    /// Sets the caller's limits for `token_id`. Tighter limits apply now,
+    /// looser or removed ones after `LOOSEN_DELAY_NS`.
+    pub fn set_limits(&mut self, token_id: AccountId, limits: Limits) {
+        let now = env::block_timestamp();
+        let player_limits = self
+            .limits
+            .entry((env::predecessor_account_id(), token_id))
+            .or_insert_with(|| PlayerLimits {
+                active: limits.clone(),
+                pending: None,
+                hours: vec![],
+                days: vec![],
+            });
+        player_limits.refresh(now);
+
+        player_limits.active = player_limits.active.tightest(&limits);
+        player_limits.pending = if player_limits.active == limits {
+            None
+        } else {
+            Some((limits, now + LOOSEN_DELAY_NS))
+        };
+    }
+
+    /// Blocks the caller from spinning for `duration_sec`. Can be extended, not shortened.
+    pub fn self_exclude(&mut self, duration_sec: u64) {
+        let excluded_until = env::block_timestamp() + duration_sec * 1_000_000_000;
+        let current = self
+            .exclusions
+            .entry(env::predecessor_account_id())
+            .or_insert(0);
+        *current = (*current).max(excluded_until);
+    }
+}
+
+// internal
+
+impl Contract {
+    pub(crate) fn check_limits(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        wager: u128,
+    ) {
+        let now = env::block_timestamp();
+        if let Some(excluded_until) = self.exclusions.get(account_id) {
+            require!(now >= *excluded_until, "self excluded");
+        }
+
+        if let Some(player_limits) = self.limits.get_mut(&(account_id.clone(), token_id.clone())) {
+            player_limits.refresh(now);
+            player_limits.assert_allows(wager, now);
+            player_limits.record(now, wager, 0);
+        }
+    }
+
+    /// Counts payouts and refunds against the player's loss limits.
+    pub(crate) fn record_limits_payout(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        payout: u128,
+    ) {
+        let now = env::block_timestamp();
+        if let Some(player_limits) = self.limits.get_mut(&(account_id.clone(), token_id.clone())) {
+            player_limits.refresh(now);
+            player_limits.record(now, 0, payout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::context;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    /// Player calls at `block_timestamp`.
+    fn setup_at_time(block_timestamp: u64) {
+        testing_env!(context(accounts(1))
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    fn daily_wager(amount: u128) -> Limits {
+        Limits {
+            daily_wager: Some(U128(amount)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tighter_limits_apply_at_once() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+
+        contract.set_limits(token_id.clone(), daily_wager(1_000));
+        contract.set_limits(token_id.clone(), daily_wager(500));
+
+        let view = contract.get_limits(accounts(1), token_id).unwrap();
+        assert_eq!(view.active, daily_wager(500));
+        assert!(view.pending.is_none());
+    }
+
+    #[test]
+    fn looser_limits_wait_for_delay() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+
+        contract.set_limits(token_id.clone(), daily_wager(500));
+        contract.set_limits(token_id.clone(), Limits::default());
+
+        let view = contract.get_limits(accounts(1), token_id.clone()).unwrap();
+        assert_eq!(view.active, daily_wager(500));
+        assert_eq!(view.pending, Some(Limits::default()));
+        assert_eq!(
+            view.pending_effective_at,
+            Some(U64(DAY_NS + LOOSEN_DELAY_NS))
+        );
+
+        contract.check_limits(&accounts(1), &token_id, 500);
+
+        setup_at_time(DAY_NS + LOOSEN_DELAY_NS);
+        contract.check_limits(&accounts(1), &token_id, 10_000);
+        let view = contract.get_limits(accounts(1), token_id).unwrap();
+        assert_eq!(view.active, Limits::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "wager limit reached")]
+    fn wager_limit_is_enforced() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        contract.set_limits(token_id.clone(), daily_wager(1_000));
+
+        contract.check_limits(&accounts(1), &token_id, 600);
+        contract.check_limits(&accounts(1), &token_id, 600);
+    }
+
+    #[test]
+    fn wager_limit_frees_up_after_24_hours() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        contract.set_limits(token_id.clone(), daily_wager(1_000));
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+
+        setup_at_time(2 * DAY_NS);
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "wager limit reached")]
+    fn wager_limit_does_not_reset_at_midnight() {
+        setup_at_time(2 * DAY_NS - HOUR_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        contract.set_limits(token_id.clone(), daily_wager(1_000));
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+
+        setup_at_time(2 * DAY_NS + HOUR_NS);
+        contract.check_limits(&accounts(1), &token_id, 1);
+    }
+
+    #[test]
+    fn payouts_count_against_loss_limit() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        let limits = Limits {
+            weekly_loss: Some(U128(1_000)),
+            ..Default::default()
+        };
+        contract.set_limits(token_id.clone(), limits);
+
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+        contract.record_limits_payout(&accounts(1), &token_id, 2_000);
+
+        // the player is ahead, so a full loss of the next wager stays in the limit
+        setup_at_time(3 * DAY_NS);
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "wager limit reached")]
+    fn weekly_limit_counts_the_current_day_in_full() {
+        setup_at_time(DAY_NS + HOUR_NS);
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        let limits = Limits {
+            weekly_wager: Some(U128(1_000)),
+            ..Default::default()
+        };
+        contract.set_limits(token_id.clone(), limits);
+        contract.check_limits(&accounts(1), &token_id, 1_000);
+
+        // 7 days later the day of the wager is still in the window
+        setup_at_time(8 * DAY_NS + HOUR_NS);
+        contract.check_limits(&accounts(1), &token_id, 1);
+    }
+
+    #[test]
+    fn old_periods_are_dropped() {
+        let mut player_limits = PlayerLimits {
+            active: daily_wager(1_000),
+            pending: None,
+            hours: vec![],
+            days: vec![],
+        };
+
+        for hour in 0..40 * DAY_HOURS {
+            let now = hour * HOUR_NS;
+            player_limits.refresh(now);
+            player_limits.record(now, 1, 0);
+        }
+
+        assert_eq!(player_limits.hours.len() as u64, DAY_HOURS);
+        assert_eq!(player_limits.days.len() as u64, MONTH_DAYS + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "self excluded")]
+    fn self_exclusion_blocks_spins() {
+        setup_at_time(DAY_NS);
+        let mut contract = Contract::default();
+        contract.self_exclude(60);
+        // shorter exclusions do not shorten it
+        contract.self_exclude(1);
+
+        setup_at_time(DAY_NS + 59 * 1_000_000_000);
+        contract.check_limits(&accounts(1), &bankroll::near_token_id(), 1);
+    }
+}
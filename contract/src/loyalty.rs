@@ -0,0 +1,247 @@
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq)]
+#[near(serializers = [json, borsh])]
+pub struct RakebackTier {
+    pub min_points: U128,
+    pub rakeback_bps: u16,
+}
+
+#[derive(Clone, Default)]
+#[near(serializers = [borsh])]
+pub struct Loyalty {
+    pub points: u128, // lifetime wagered
+    pub unclaimed: u128,
+    pub claimed: u128,
+}
+
+#[near(serializers = [json])]
+pub struct LoyaltyView {
+    pub points: U128,
+    pub rakeback_bps: u16,
+    pub unclaimed: U128,
+    pub claimed: U128,
+}
+
+/// Rakeback of the highest tier `points` reach, 0 below the first tier.
+pub fn rakeback_bps(tiers: &[RakebackTier], points: u128) -> u16 {
+    tiers
+        .iter()
+        .rev()
+        .find(|tier| points >= tier.min_points.0)
+        .map_or(0, |tier| tier.rakeback_bps)
+}
+
+#[near]
+impl Contract {
+    pub fn get_loyalty(&self, account_id: AccountId, token_id: AccountId) -> LoyaltyView {
+        let loyalty = self
+            .loyalty
+            .get(&(account_id, token_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+
+        LoyaltyView {
+            points: U128(loyalty.points),
+            rakeback_bps: rakeback_bps(&self.get_rakeback_tiers(token_id), loyalty.points),
+            unclaimed: U128(loyalty.unclaimed),
+            claimed: U128(loyalty.claimed),
+        }
+    }
+
+    pub fn get_rakeback_tiers(&self, token_id: AccountId) -> Vec<RakebackTier> {
+        self.rakeback_tiers
+            .get(&token_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Points are in units of `token_id`, tiers must be sorted by `min_points`.
+    pub fn set_rakeback_tiers(&mut self, token_id: AccountId, tiers: Vec<RakebackTier>) {
+        self.assert_owner();
+        require!(
+            tiers
+                .windows(2)
+                .all(|pair| pair[0].min_points.0 < pair[1].min_points.0),
+            "tiers not sorted by min_points"
+        );
+        require!(
+            tiers.iter().all(|tier| tier.rakeback_bps <= 10_000),
+            "rakeback above 100%"
+        );
+
+        if tiers.is_empty() {
+            self.rakeback_tiers.remove(&token_id);
+        } else {
+            self.rakeback_tiers.insert(token_id, tiers);
+        }
+    }
+
+    /// Pays out accrued rakeback, it left the bankroll when it accrued.
+    pub fn claim_rakeback(&mut self, token_id: AccountId) -> Promise {
+        self.assert_withdrawals_not_paused();
+        let account_id = env::predecessor_account_id();
+        let loyalty = self
+            .loyalty
+            .get_mut(&(account_id.clone(), token_id.clone()))
+            .expect("nothing to claim");
+        let amount = std::mem::take(&mut loyalty.unclaimed);
+        loyalty.claimed += amount;
+
+        self.pay_out(token_id, account_id, amount)
+            .expect("nothing to claim")
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Adds `wager` to the player's points and moves rakeback at their new tier out of
+    /// the free bankroll, like referral rewards. Tokens without tiers keep no points.
+    pub(crate) fn accrue_loyalty(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        wager: u128,
+    ) {
+        let tiers = self.get_rakeback_tiers(token_id.clone());
+        if tiers.is_empty() {
+            return;
+        }
+        let key = (account_id.clone(), token_id.clone());
+        let points = self
+            .loyalty
+            .get(&key)
+            .map_or(0, |loyalty| loyalty.points)
+            .checked_add(wager)
+            .expect("points overflow");
+        let rakeback = wager * rakeback_bps(&tiers, points) as u128 / 10_000;
+        let rakeback = self.reserve_bankroll(token_id, rakeback);
+
+        let loyalty = self.loyalty.entry(key).or_default();
+        loyalty.points = points;
+        loyalty.unclaimed += rakeback;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::setup;
+    use near_sdk::test_utils::accounts;
+
+    fn tiers() -> Vec<RakebackTier> {
+        vec![
+            RakebackTier {
+                min_points: U128(1_000),
+                rakeback_bps: 10,
+            },
+            RakebackTier {
+                min_points: U128(10_000),
+                rakeback_bps: 50,
+            },
+        ]
+    }
+
+    #[test]
+    fn tier_by_points() {
+        assert_eq!(rakeback_bps(&tiers(), 0), 0);
+        assert_eq!(rakeback_bps(&tiers(), 999), 0);
+        assert_eq!(rakeback_bps(&tiers(), 1_000), 10);
+        assert_eq!(rakeback_bps(&tiers(), 9_999), 10);
+        assert_eq!(rakeback_bps(&tiers(), 10_000), 50);
+        assert_eq!(rakeback_bps(&[], 10_000), 0);
+    }
+
+    #[test]
+    fn rakeback_accrues_at_current_tier() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        contract.set_rakeback_tiers(token_id.clone(), tiers());
+
+        contract.accrue_loyalty(&accounts(1), &token_id, 500);
+        assert_eq!(
+            contract
+                .get_loyalty(accounts(1), token_id.clone())
+                .unclaimed
+                .0,
+            0
+        );
+
+        contract.accrue_loyalty(&accounts(1), &token_id, 1_000);
+        contract.accrue_loyalty(&accounts(1), &token_id, 10_000);
+
+        let loyalty = contract.get_loyalty(accounts(1), token_id.clone());
+        assert_eq!(loyalty.points.0, 11_500);
+        assert_eq!(loyalty.rakeback_bps, 50);
+        assert_eq!(loyalty.unclaimed.0, 1 + 50);
+
+        // other tokens keep their own points
+        assert_eq!(contract.get_loyalty(accounts(1), accounts(2)).points.0, 0);
+    }
+
+    #[test]
+    fn rakeback_leaves_bankroll_when_accrued() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+        contract.set_rakeback_tiers(token_id.clone(), tiers());
+        let bankroll = contract.get_bankroll(token_id.clone()).0;
+        contract.accrue_loyalty(&accounts(1), &token_id, 20_000);
+        assert_eq!(contract.get_bankroll(token_id.clone()).0, bankroll - 100);
+
+        setup(accounts(1));
+        contract.claim_rakeback(token_id.clone());
+
+        let loyalty = contract.get_loyalty(accounts(1), token_id.clone());
+        assert_eq!(loyalty.unclaimed.0, 0);
+        assert_eq!(loyalty.claimed.0, 100);
+        assert_eq!(contract.get_bankroll(token_id).0, bankroll - 100);
+    }
+
+    #[test]
+    fn rakeback_limited_to_free_bankroll() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let token_id = accounts(3);
+        contract.set_rakeback_tiers(token_id.clone(), tiers());
+        contract.lock_liability(&token_id, 100, 90);
+
+        contract.accrue_loyalty(&accounts(1), &token_id, 20_000);
+
+        let loyalty = contract.get_loyalty(accounts(1), token_id.clone());
+        assert_eq!(loyalty.points.0, 20_000);
+        assert_eq!(loyalty.unclaimed.0, 10);
+        assert_eq!(contract.get_bankroll(token_id).0, 90);
+    }
+
+    #[test]
+    fn no_points_without_tiers() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let token_id = bankroll::near_token_id();
+
+        contract.accrue_loyalty(&accounts(1), &token_id, 20_000);
+
+        assert!(contract.loyalty.get(&(accounts(1), token_id)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "tiers not sorted by min_points")]
+    fn unsorted_tiers_are_rejected() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        let mut tiers = tiers();
+        tiers.reverse();
+        contract.set_rakeback_tiers(bankroll::near_token_id(), tiers);
+    }
+
+    #[test]
+    #[should_panic(expected = "only owner")]
+    fn only_owner_sets_tiers() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        contract.set_rakeback_tiers(bankroll::near_token_id(), tiers());
+    }
+}
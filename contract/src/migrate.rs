@@ -0,0 +1,190 @@
+use crate::*;
+
+// state layout version, stored next to the contract state
+const VERSION_KEY: &[u8] = b"VERSION";
+pub const STATE_VERSION: u8 = 1;
+
+/// Layout of the first release, `house` and `payout` were NEAR and FT amounts mixed.
+#[near(serializers = [borsh])]
+pub struct ContractV0 {
+    spins: u128,
+    bets: u128,
+    house: u128,
+    payout: u128,
+    balances: LookupMap<String, LookupMap<AccountId, u128>>,
+}
+
+impl From<ContractV0> for Contract {
+    /// Moves `house` into the NEAR bankroll and `payout` into the NEAR paid stats.
+    fn from(old: ContractV0) -> Self {
+        let near_token_id = bankroll::near_token_id();
+
+        let mut this = Contract {
+            spins: old.spins,
+            bets: old.bets,
+            next_spin_id: old.spins as u64,
+            balances: old.balances,
+            ..Default::default()
+        };
+
+        this.bankroll.insert(near_token_id.clone(), old.house);
+        let mut near_stats = stats::TokenStats::new();
+        near_stats.paid = old.payout;
+        this.token_stats.insert(near_token_id, near_stats);
+
+        this
+    }
+}
+
+/// Every state layout the contract has been released with.
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(Contract),
+}
+
+impl VersionedContract {
+    /// Reads the stored state with the layout of its recorded version.
+    /// Unversioned state is either the first release or a fresh deployment
+    /// that never migrated, the layouts have different lengths.
+    pub fn read() -> Self {
+        let state = env::storage_read(b"STATE").expect("no state to migrate");
+        match env::storage_read(VERSION_KEY).map(|version| version[0]) {
+            None => ContractV0::try_from_slice(&state)
+                .map(Self::V0)
+                .unwrap_or_else(|_| {
+                    Self::V1(Contract::try_from_slice(&state).expect("unknown state layout"))
+                }),
+            Some(STATE_VERSION) => {
+                Self::V1(Contract::try_from_slice(&state).expect("unknown state layout"))
+            }
+            Some(_) => env::panic_str("unknown state version"),
+        }
+    }
+
+    pub fn owner_id(&self) -> AccountId {
+        match self {
+            Self::V0(_) => env::current_account_id(),
+            Self::V1(state) => state.owner_id.clone(),
+        }
+    }
+
+    pub fn into_current(self) -> Contract {
+        match self {
+            Self::V0(state) => state.into(),
+            Self::V1(state) => state,
+        }
+    }
+}
+
+fn write_state_version() {
+    env::storage_write(VERSION_KEY, &[STATE_VERSION]);
+}
+
+#[near]
+impl Contract {
+    /// Converts the stored state to the current layout, called after deploying new code.
+    /// Only the owner or the contract account itself can migrate.
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = VersionedContract::read();
+
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            predecessor_id == env::current_account_id() || predecessor_id == state.owner_id(),
+            "only owner"
+        );
+
+        write_state_version();
+        state.into_current()
+    }
+
+    /// Layout version of the state, loading it already needs the current layout.
+    pub fn get_state_version(&self) -> u8 {
+        env::storage_read(VERSION_KEY).map_or(STATE_VERSION, |version| version[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::setup;
+    use near_sdk::test_utils::accounts;
+
+    fn write_v0_state() {
+        let mut balances = LookupMap::new(b"a");
+        balances.set("usdc.fakes.testnet".to_owned(), Some(LookupMap::new(b"b")));
+        env::state_write(&ContractV0 {
+            spins: 12,
+            bets: 150,
+            house: 90_000,
+            payout: 10_000,
+            balances,
+        });
+    }
+
+    #[test]
+    fn migrate_v0_moves_house_and_payout_to_near() {
+        setup(accounts(0));
+        write_v0_state();
+
+        let contract = Contract::migrate();
+
+        let near_token_id = bankroll::near_token_id();
+        assert_eq!(contract.get_bankroll(near_token_id.clone()).0, 90_000);
+        assert_eq!(contract.stats_v2(near_token_id).paid.0, 10_000);
+        assert_eq!(
+            contract.stats(),
+            (U128(12), U128(150), U128(90_000), U128(10_000))
+        );
+        assert_eq!(contract.get_owner(), accounts(0));
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.usdc_balance(accounts(1)).0, 0);
+    }
+
+    #[test]
+    fn migrate_current_version_keeps_state() {
+        setup(accounts(0));
+        {
+            let mut contract = Contract::default();
+            contract.set_owner(accounts(1));
+            contract.add_bankroll(&bankroll::near_token_id(), 5);
+            env::state_write(&contract);
+        }
+
+        setup(accounts(1));
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_owner(), accounts(1));
+        assert_eq!(
+            contract.get_bankroll(bankroll::near_token_id()).0,
+            100_000_000_000_000_000_000_000_005
+        );
+    }
+
+    #[test]
+    fn migrate_unversioned_current_state() {
+        setup(accounts(0));
+        {
+            let mut contract = Contract::default();
+            contract.set_pending_timeout_blocks(U64(500));
+            env::state_write(&contract);
+        }
+        assert!(env::storage_read(VERSION_KEY).is_none());
+
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_pending_timeout_blocks().0, 500);
+        assert!(contract.get_staged_upgrade().is_none());
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(env::storage_read(VERSION_KEY), Some(vec![STATE_VERSION]));
+    }
+
+    #[test]
+    #[should_panic(expected = "only owner")]
+    fn migrate_rejects_other_accounts() {
+        setup(accounts(2));
+        write_v0_state();
+
+        Contract::migrate();
+    }
+}
@@ -0,0 +1,131 @@
+use crate::*;
+
+#[derive(Clone, Default)]
+#[near(serializers = [json, borsh])]
+pub struct Paused {
+    pub spins: bool,
+    pub withdrawals: bool,
+    pub tokens: Vec<AccountId>,
+}
+
+#[derive(Debug)]
+#[near(serializers = [json])]
+pub enum PauseScope {
+    Spins,
+    Withdrawals,
+    Token(AccountId),
+}
+
+#[near]
+impl Contract {
+    pub fn get_paused(&self) -> Paused {
+        self.paused.clone()
+    }
+
+    pub fn get_guardians(&self) -> Vec<AccountId> {
+        self.guardians.iter().cloned().collect()
+    }
+
+    pub fn add_guardian(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.guardians.insert(account_id);
+    }
+
+    pub fn remove_guardian(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.guardians.remove(&account_id);
+    }
+
+    /// Guardians and the owner can pause, only the owner can unpause.
+    /// Spins already waiting on their signature are still settled while paused.
+    pub fn set_paused(&mut self, scope: PauseScope, paused: bool) {
+        let predecessor_id = env::predecessor_account_id();
+        if paused {
+            require!(
+                predecessor_id == self.owner_id || self.guardians.contains(&predecessor_id),
+                "only guardian"
+            );
+        } else {
+            self.assert_owner();
+        }
+
+        log!("{} set {:?} to {}", predecessor_id, scope, paused);
+
+        match scope {
+            PauseScope::Spins => self.paused.spins = paused,
+            PauseScope::Withdrawals => self.paused.withdrawals = paused,
+            PauseScope::Token(token_id) => {
+                self.paused
+                    .tokens
+                    .retain(|paused_id| *paused_id != token_id);
+                if paused {
+                    self.paused.tokens.push(token_id);
+                }
+            }
+        }
+    }
+}
+
+// internal
+
+impl Contract {
+    pub(crate) fn assert_spins_not_paused(&self, token_id: &AccountId) {
+        require!(!self.paused.spins, "spins paused");
+        require!(!self.paused.tokens.contains(token_id), "token paused");
+    }
+
+    pub(crate) fn assert_withdrawals_not_paused(&self) {
+        require!(!self.paused.withdrawals, "withdrawals paused");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::setup;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn guardian_pauses_token() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        contract.add_guardian(accounts(1));
+
+        setup(accounts(1));
+        contract.set_paused(PauseScope::Token(accounts(2)), true);
+        assert_eq!(contract.get_paused().tokens, vec![accounts(2)]);
+        assert!(!contract.get_paused().spins);
+
+        setup(accounts(0));
+        contract.set_paused(PauseScope::Token(accounts(2)), false);
+        assert!(contract.get_paused().tokens.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "only owner")]
+    fn guardian_cannot_unpause() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        contract.add_guardian(accounts(1));
+
+        setup(accounts(1));
+        contract.set_paused(PauseScope::Spins, true);
+        contract.set_paused(PauseScope::Spins, false);
+    }
+
+    #[test]
+    #[should_panic(expected = "spins paused")]
+    fn paused_spins_reject_bets() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        contract.set_paused(PauseScope::Spins, true);
+
+        contract.spin(
+            vec![],
+            accounts(1),
+            0,
+            bankroll::near_token_id(),
+            SpinOptions::default(),
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::*;
+
+pub const MAX_RECIPIENTS: usize = 10;
+
+#[derive(Debug)]
+#[near(serializers = [json, borsh])]
+pub struct Recipient {
+    pub account_id: AccountId,
+    pub weight: u16,
+}
+
+/// Who receives the winnings of a spin instead of the player.
+/// In JSON either an account id or a list of weighted recipients.
+#[derive(Debug)]
+#[near(serializers = [json, borsh])]
+#[serde(untagged)]
+pub enum PayoutTo {
+    Account(AccountId),
+    Split(Vec<Recipient>),
+}
+
+impl PayoutTo {
+    pub fn assert_valid(&self) {
+        if let PayoutTo::Split(recipients) = self {
+            require!(
+                !recipients.is_empty() && recipients.len() <= MAX_RECIPIENTS,
+                "payout_to needs 1 to 10 recipients"
+            );
+            require!(
+                recipients.iter().all(|recipient| recipient.weight > 0),
+                "recipient weight must be positive"
+            );
+        }
+    }
+
+    pub fn recipient_count(&self) -> usize {
+        match self {
+            PayoutTo::Account(_) => 1,
+            PayoutTo::Split(recipients) => recipients.len(),
+        }
+    }
+
+    /// Splits `amount` by weight, the last recipient gets the rounding remainder.
+    pub fn shares(&self, amount: u128) -> Vec<(AccountId, u128)> {
+        match self {
+            PayoutTo::Account(account_id) => vec![(account_id.clone(), amount)],
+            PayoutTo::Split(recipients) => {
+                let total_weight: u128 = recipients.iter().map(|r| r.weight as u128).sum();
+                let mut remaining = amount;
+                let mut shares = vec![];
+                for (i, recipient) in recipients.iter().enumerate() {
+                    let share = if i == recipients.len() - 1 {
+                        remaining
+                    } else {
+                        amount / total_weight * recipient.weight as u128
+                            + amount % total_weight * recipient.weight as u128 / total_weight
+                    };
+                    remaining -= share;
+                    shares.push((recipient.account_id.clone(), share));
+                }
+                shares
+            }
+        }
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Pays winnings to `payout_to`, or to the player when not set.
+    /// Players holding their winnings get them credited to claimable instead.
+    pub(crate) fn pay_winnings(
+        &mut self,
+        token_id: AccountId,
+        account_id: AccountId,
+        payout_to: Option<PayoutTo>,
+        amount: u128,
+    ) {
+        if payout_to.is_none() && amount > 0 && self.holding_winnings.contains(&account_id) {
+            self.add_claimable(account_id, token_id, amount);
+            return;
+        }
+
+        let payout_to = payout_to.unwrap_or(PayoutTo::Account(account_id));
+        for (receiver_id, share) in payout_to.shares(amount) {
+            self.pay_out(token_id.clone(), receiver_id, share);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn shares_add_up_to_amount() {
+        let payout_to = PayoutTo::Split(vec![
+            Recipient {
+                account_id: accounts(0),
+                weight: 1,
+            },
+            Recipient {
+                account_id: accounts(1),
+                weight: 1,
+            },
+            Recipient {
+                account_id: accounts(2),
+                weight: 1,
+            },
+        ]);
+
+        assert_eq!(
+            payout_to.shares(100),
+            vec![(accounts(0), 33), (accounts(1), 33), (accounts(2), 34)]
+        );
+        assert_eq!(
+            PayoutTo::Account(accounts(3)).shares(100),
+            vec![(accounts(3), 100)]
+        );
+    }
+
+    #[test]
+    fn payout_to_from_json() {
+        let account: PayoutTo = serde_json::from_str("\"bob.near\"").unwrap();
+        assert_eq!(account.recipient_count(), 1);
+
+        let split: PayoutTo = serde_json::from_str(
+            r#"[{"account_id": "bob.near", "weight": 9}, {"account_id": "alice.near", "weight": 1}]"#,
+        )
+        .unwrap();
+        assert_eq!(split.shares(10)[0], ("bob.near".parse().unwrap(), 9));
+    }
+
+    #[test]
+    #[should_panic(expected = "recipient weight must be positive")]
+    fn zero_weight_is_rejected() {
+        PayoutTo::Split(vec![Recipient {
+            account_id: accounts(0),
+            weight: 0,
+        }])
+        .assert_valid();
+    }
+}
@@ -0,0 +1,174 @@
+use crate::*;
+
+// MPC signatures resolve or time out within ~200 blocks
+pub const MPC_TIMEOUT_BLOCKS: u64 = 200;
+pub const DEFAULT_PENDING_TIMEOUT_BLOCKS: u64 = 300;
+
+/// Spins from `spin` waiting on `mpc_callback`, removed when settled or reclaimed.
+#[near(serializers = [json, borsh])]
+pub struct PendingSpin {
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub spins: Vec<Vec<roulette::Bet>>,
+    pub amount: U128, // wager
+    pub liability: U128,
+    pub block_height: U64,
+    pub payout_to: Option<payout::PayoutTo>,
+    pub stop: Option<autoplay::StopConditions>,
+    pub strategy: Option<roulette::Strategy>, // `spins` hold the worst case bets
+    pub storage_bytes: U64, // of the player's storage deposit, for this entry and the records
+}
+
+#[near]
+impl Contract {
+    pub fn get_pending_spin(&self, spin_id: U64) -> Option<&PendingSpin> {
+        self.pending_spins.get(&spin_id.0)
+    }
+
+    pub fn get_pending_timeout_blocks(&self) -> U64 {
+        U64(self.pending_timeout_blocks)
+    }
+
+    /// At least the MPC timeout, a spin reclaimed earlier could still be settled
+    /// with a result the player already saw in the signer's response.
+    pub fn set_pending_timeout_blocks(&mut self, timeout_blocks: U64) {
+        self.assert_owner();
+        require!(
+            timeout_blocks.0 > MPC_TIMEOUT_BLOCKS,
+            "timeout within the mpc timeout"
+        );
+        self.pending_timeout_blocks = timeout_blocks.0;
+    }
+
+    /// Refunds the wager of a spin that was not settled within the timeout,
+    /// e.g. when `mpc_callback` ran out of gas.
+    pub fn reclaim_pending(&mut self, spin_id: U64) {
+        let pending = self
+            .pending_spins
+            .remove(&spin_id.0)
+            .expect("no pending spin");
+        require!(
+            env::block_height() >= pending.block_height.0 + self.pending_timeout_blocks,
+            "pending spin not timed out"
+        );
+
+        log!("spin {} reclaimed", spin_id.0);
+        self.refund_pending(pending);
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Refunds the wager, the spins are taken out of the stats as never played.
+    pub(crate) fn refund_pending(&mut self, pending: PendingSpin) -> Option<Promise> {
+        self.release_storage(&pending.account_id, pending.storage_bytes.0);
+        self.remove_spins(&pending.token_id, &pending.spins);
+        self.settle_liability(&pending.token_id, pending.liability.0, pending.amount.0);
+        self.record_limits_payout(&pending.account_id, &pending.token_id, pending.amount.0);
+        self.pay_out(pending.token_id, pending.account_id, pending.amount.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{context, deposit_storage, setup};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    const BANKROLL: u128 = 100_000_000_000_000_000_000_000_000;
+    const WAGER: u128 = 1_000_000_000_000_000_000_000_000;
+
+    /// Player calls at `block_height`.
+    fn setup_at_height(block_height: u64) {
+        testing_env!(context(accounts(1)).block_height(block_height).build());
+    }
+
+    /// Contract with the storage of `accounts(1)` covered, called at `block_height`.
+    fn contract_at_height(block_height: u64) -> Contract {
+        let mut contract = Contract::default();
+        deposit_storage(&mut contract, accounts(1));
+        setup_at_height(block_height);
+        contract
+    }
+
+    fn spin_red(contract: &mut Contract) {
+        let bet = roulette::Bet {
+            kind: roulette::BetKind::Red,
+            amount: NearToken::from_yoctonear(WAGER),
+            number: 0,
+        };
+        contract.spin(
+            vec![vec![bet]],
+            accounts(1),
+            WAGER,
+            bankroll::near_token_id(),
+            SpinOptions::default(),
+        );
+    }
+
+    #[test]
+    fn reclaim_after_timeout_refunds_wager() {
+        let mut contract = contract_at_height(10);
+        spin_red(&mut contract);
+
+        let near_token_id = bankroll::near_token_id();
+        let pending = contract.get_pending_spin(U64(0)).unwrap();
+        assert_eq!(pending.amount.0, WAGER);
+        assert_eq!(pending.liability.0, 2 * WAGER);
+        assert_eq!(
+            contract.get_bankroll(near_token_id.clone()).0,
+            BANKROLL + WAGER
+        );
+
+        setup_at_height(10 + DEFAULT_PENDING_TIMEOUT_BLOCKS);
+        contract.reclaim_pending(U64(0));
+
+        assert!(contract.get_pending_spin(U64(0)).is_none());
+        assert_eq!(contract.get_bankroll(near_token_id.clone()).0, BANKROLL);
+        assert_eq!(contract.get_liability(near_token_id.clone()).0, 0);
+
+        // counted as never played, the next spin still gets a new id
+        let stats = contract.stats_v2(near_token_id);
+        assert_eq!((stats.spins.0, stats.bets.0, stats.wagered.0), (0, 0, 0));
+        assert_eq!(contract.stats().0 .0, 0);
+        assert_eq!(contract.stats().1 .0, 0);
+        spin_red(&mut contract);
+        assert!(contract.get_pending_spin(U64(1)).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "timeout within the mpc timeout")]
+    fn timeout_below_mpc_timeout() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        contract.set_pending_timeout_blocks(U64(MPC_TIMEOUT_BLOCKS));
+    }
+
+    #[test]
+    #[should_panic(expected = "pending spin not timed out")]
+    fn reclaim_before_timeout() {
+        let mut contract = contract_at_height(10);
+        spin_red(&mut contract);
+
+        setup_at_height(9 + DEFAULT_PENDING_TIMEOUT_BLOCKS);
+        contract.reclaim_pending(U64(0));
+    }
+
+    #[test]
+    fn failed_signature_refunds_wager() {
+        let mut contract = contract_at_height(10);
+        spin_red(&mut contract);
+
+        contract.mpc_callback(Err(PromiseError::Failed), 0);
+
+        assert!(contract.get_pending_spin(U64(0)).is_none());
+        assert_eq!(contract.get_bankroll(bankroll::near_token_id()).0, BANKROLL);
+
+        // a late callback finds nothing to settle
+        assert!(contract
+            .mpc_callback(Err(PromiseError::Failed), 0)
+            .is_empty());
+    }
+}
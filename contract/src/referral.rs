@@ -0,0 +1,230 @@
+use crate::*;
+
+pub const DEFAULT_REFERRAL_SHARE_BPS: u16 = 1_000;
+
+#[derive(Clone, Default)]
+#[near(serializers = [json, borsh])]
+pub struct ReferralStats {
+    pub wagered: U128, // settled wagers of referred players
+    pub earned: U128,
+    pub unclaimed: U128,
+}
+
+/// Net house profit on a referred player in one token, their wins carry forward.
+#[derive(Clone, Default)]
+#[near(serializers = [borsh])]
+pub struct HouseProfit {
+    pub net: i128,
+    pub high: i128, // highest `net` the referrer was paid on
+}
+
+#[near]
+impl Contract {
+    pub fn get_referrer(&self, account_id: AccountId) -> Option<AccountId> {
+        self.referrers.get(&account_id).cloned()
+    }
+
+    pub fn get_referred_count(&self, referrer_id: AccountId) -> u32 {
+        *self.referred_count.get(&referrer_id).unwrap_or(&0)
+    }
+
+    pub fn get_referral_stats(&self, referrer_id: AccountId, token_id: AccountId) -> ReferralStats {
+        self.referral_stats
+            .get(&(referrer_id, token_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn get_referral_share_bps(&self) -> u16 {
+        self.referral_share_bps
+    }
+
+    /// Share of the net house profit on referred players that goes to their referrer.
+    pub fn set_referral_share_bps(&mut self, share_bps: u16) {
+        self.assert_owner();
+        require!(share_bps <= 10_000, "share above 100%");
+        self.referral_share_bps = share_bps;
+    }
+
+    pub fn claim_referral_rewards(&mut self, token_id: AccountId) -> Promise {
+        self.assert_withdrawals_not_paused();
+        let referrer_id = env::predecessor_account_id();
+        let stats = self
+            .referral_stats
+            .get_mut(&(referrer_id.clone(), token_id.clone()))
+            .expect("nothing to claim");
+        let amount = std::mem::take(&mut stats.unclaimed).0;
+
+        self.pay_out(token_id, referrer_id, amount)
+            .expect("nothing to claim")
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Sets the referrer of `account_id` on their first bet, later referrers are ignored.
+    /// Players who already bet keep playing without a referrer.
+    pub(crate) fn register_referrer(
+        &mut self,
+        account_id: &AccountId,
+        referrer_id: Option<AccountId>,
+    ) {
+        let Some(referrer_id) = referrer_id else {
+            return;
+        };
+        if referrer_id == *account_id
+            || self.referrers.contains_key(account_id)
+            || self.bettors.contains(account_id)
+        {
+            return;
+        }
+
+        self.referrers
+            .insert(account_id.clone(), referrer_id.clone());
+        *self.referred_count.entry(referrer_id).or_insert(0) += 1;
+    }
+
+    /// Moves the referrer share of new house profit on the player out of the free bankroll.
+    /// Profit counts once the player's net losses pass their previous high, so wins
+    /// carry forward. Profit the free bankroll cannot cover is not shared.
+    pub(crate) fn accrue_referral(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        wager: u128,
+        payout: u128,
+    ) {
+        let Some(referrer_id) = self.referrers.get(account_id).cloned() else {
+            return;
+        };
+
+        let house_profit = self
+            .referral_profit
+            .entry((account_id.clone(), token_id.clone()))
+            .or_default();
+        house_profit.net += wager as i128 - payout as i128;
+        let profit = house_profit.net.saturating_sub(house_profit.high).max(0) as u128;
+        house_profit.high = house_profit.high.max(house_profit.net);
+
+        let share = profit * self.referral_share_bps as u128 / 10_000;
+        let share = self.reserve_bankroll(token_id, share);
+
+        let stats = self
+            .referral_stats
+            .entry((referrer_id, token_id.clone()))
+            .or_default();
+        stats.wagered = U128(stats.wagered.0 + wager);
+        stats.earned = U128(stats.earned.0 + share);
+        stats.unclaimed = U128(stats.unclaimed.0 + share);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit_storage, setup, setup_with_deposit};
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn referrer_earns_share_of_house_profit() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        let near_token_id = bankroll::near_token_id();
+        let bankroll = contract.get_bankroll(near_token_id.clone()).0;
+
+        contract.register_referrer(&accounts(1), Some(accounts(2)));
+        // only the first referrer counts
+        contract.register_referrer(&accounts(1), Some(accounts(3)));
+        assert_eq!(contract.get_referrer(accounts(1)), Some(accounts(2)));
+        assert_eq!(contract.get_referred_count(accounts(2)), 1);
+
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 200);
+        // no share when the player wins
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 2_000);
+
+        let stats = contract.get_referral_stats(accounts(2), near_token_id.clone());
+        assert_eq!(stats.wagered.0, 2_000);
+        assert_eq!(stats.earned.0, 80);
+        assert_eq!(stats.unclaimed.0, 80);
+        assert_eq!(
+            contract.get_bankroll(near_token_id.clone()).0,
+            bankroll - 80
+        );
+
+        setup(accounts(2));
+        contract.claim_referral_rewards(near_token_id.clone());
+        let stats = contract.get_referral_stats(accounts(2), near_token_id);
+        assert_eq!(stats.unclaimed.0, 0);
+        assert_eq!(stats.earned.0, 80);
+    }
+
+    #[test]
+    fn referrer_earns_nothing_on_profit_won_back() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        let near_token_id = bankroll::near_token_id();
+        contract.register_referrer(&accounts(1), Some(accounts(2)));
+        let earned = |contract: &Contract| {
+            contract
+                .get_referral_stats(accounts(2), near_token_id.clone())
+                .earned
+                .0
+        };
+
+        // wins first, then loses them back
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 2_000);
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 0);
+        assert_eq!(earned(&contract), 0);
+
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 0);
+        assert_eq!(earned(&contract), 100);
+
+        // loses, wins it back and loses it again
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 2_000);
+        contract.accrue_referral(&accounts(1), &near_token_id, 1_000, 0);
+        assert_eq!(earned(&contract), 100);
+    }
+
+    #[test]
+    fn referral_share_limited_to_free_bankroll() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        let token_id = accounts(3);
+        contract.lock_liability(&token_id, 100, 90);
+        contract.register_referrer(&accounts(1), Some(accounts(2)));
+
+        contract.accrue_referral(&accounts(1), &token_id, 1_000, 0);
+
+        let stats = contract.get_referral_stats(accounts(2), token_id.clone());
+        assert_eq!(stats.unclaimed.0, 10);
+        assert_eq!(contract.get_bankroll(token_id).0, 90);
+    }
+
+    #[test]
+    fn referrer_only_set_on_first_bet() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        deposit_storage(&mut contract, accounts(1));
+        setup_with_deposit(accounts(1), 10);
+        let red = vec![vec![roulette::Bet {
+            kind: roulette::BetKind::Red,
+            amount: NearToken::from_yoctonear(10),
+            number: 0,
+        }]];
+
+        contract.spin_with_near(red.clone(), None, None, None);
+        contract.spin_with_near(red, None, None, Some(accounts(2)));
+
+        assert_eq!(contract.get_referrer(accounts(1)), None);
+        assert_eq!(contract.get_referred_count(accounts(2)), 0);
+    }
+
+    #[test]
+    fn players_cannot_refer_themselves() {
+        setup(accounts(1));
+        let mut contract = Contract::default();
+        contract.register_referrer(&accounts(1), Some(accounts(1)));
+        assert_eq!(contract.get_referrer(accounts(1)), None);
+    }
+}
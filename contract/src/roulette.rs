@@ -1,6 +1,6 @@
 use crate::*;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[near(serializers = [json, borsh])]
 pub enum BetKind {
     // Inside Bets
@@ -20,7 +20,33 @@ pub enum BetKind {
     High,
 }
 
-#[derive(Debug)]
+impl BetKind {
+    pub const ALL: [BetKind; 13] = [
+        BetKind::Straight,
+        BetKind::Split,
+        BetKind::Street,
+        BetKind::Corner,
+        BetKind::SixLine,
+        BetKind::Column,
+        BetKind::Dozen,
+        BetKind::Red,
+        BetKind::Black,
+        BetKind::Odd,
+        BetKind::Even,
+        BetKind::Low,
+        BetKind::High,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[near(serializers = [json, borsh])]
+pub enum Color {
+    Green,
+    Red,
+    Black,
+}
+
+#[derive(Clone, Debug)]
 #[near(serializers = [json, borsh])]
 pub struct Bet {
     pub kind: BetKind,
@@ -46,9 +72,22 @@ pub fn bet_legal(bet: &Bet) -> bool {
     }
 }
 
+/// Pocket number the ball lands in for `rng_val`.
+pub fn pocket(rng_val: u8) -> u8 {
+    WHEEL_MAPPING[(rng_val % 37) as usize]
+}
+
+pub fn number_color(number: u8) -> Color {
+    match WHEEL_MAPPING.iter().position(|n| *n == number) {
+        Some(index) if index % 2 == 1 => Color::Red,
+        Some(index) if index > 0 => Color::Black,
+        _ => Color::Green,
+    }
+}
+
 pub fn bet_eval(rng_val: u8, bet: &Bet) -> (bool, u8, bool, u8) {
     let index = rng_val % 37;
-    let number = WHEEL_MAPPING[index as usize];
+    let number = pocket(rng_val);
     let red: bool = index % 2 == 1;
     let (win, multiple) = match bet.kind {
         BetKind::Straight => (bet.number == number, 35),
@@ -58,17 +97,17 @@ pub fn bet_eval(rng_val: u8, bet: &Bet) -> (bool, u8, bool, u8) {
         }
         BetKind::Street => {
             let (a, b, c) = STREET_BETS[bet.number as usize];
-            let numbers = vec![a, b, c];
+            let numbers = [a, b, c];
             (numbers.contains(&number), 11)
         }
         BetKind::Corner => {
             let (a, b, c, d) = CORNER_BETS[bet.number as usize];
-            let numbers = vec![a, b, c, d];
+            let numbers = [a, b, c, d];
             (numbers.contains(&number), 8)
         }
         BetKind::SixLine => {
             let (a, b, c, d, e, f) = SIX_LINE_BETS[bet.number as usize];
-            let numbers = vec![a, b, c, d, e, f];
+            let numbers = [a, b, c, d, e, f];
             (numbers.contains(&number), 5)
         }
         BetKind::Column => (number > 0 && (number - 1) % 3 == bet.number, 2),
@@ -88,6 +127,128 @@ pub fn bet_eval(rng_val: u8, bet: &Bet) -> (bool, u8, bool, u8) {
     }
 }
 
+/// Largest total payout (stake included) of `bets` on a single spin over all pockets.
+pub fn max_payout(bets: &[Bet]) -> u128 {
+    (0..37u8)
+        .map(|rng_val| {
+            bets.iter().fold(0u128, |total, bet| {
+                let (_, _, _, multiple) = bet_eval(rng_val, bet);
+                if multiple == 0 {
+                    return total;
+                }
+                let bet_payout = bet
+                    .amount
+                    .as_yoctonear()
+                    .checked_mul((multiple + 1) as u128)
+                    .expect("payout overflow");
+                total.checked_add(bet_payout).expect("payout overflow")
+            })
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Random byte values a spin lands on, `pocket` maps them with `% 37`.
+pub const RNG_VALUES: u16 = 256;
+
+/// Exact odds of a bet, `expected_value` is per unit staked.
+/// `rng_val % 37` is not uniform: wheel indices 0..=33 take 7 of the 256 byte
+/// values and 34..=36 take 6, the odds count byte values rather than pockets.
+#[derive(Clone, Debug, PartialEq)]
+#[near(serializers = [json])]
+pub struct Odds {
+    pub winning_pockets: u8,
+    pub winning_values: u16, // of the `RNG_VALUES` random byte values
+    pub probability: f64,
+    pub payout_multiple: u8, // winnings per unit staked, stake returned on top
+    pub expected_value: f64,
+}
+
+pub fn odds(bet: &Bet) -> Odds {
+    let winning_pockets = (0..37u8)
+        .filter(|rng_val| bet_eval(*rng_val, bet).0)
+        .count() as u8;
+    let (winning_values, payout_multiple) =
+        (0..=u8::MAX).fold((0, 0), |(wins, payout_multiple), rng_val| {
+            match bet_eval(rng_val, bet) {
+                (true, _, _, multiple) => (wins + 1, multiple),
+                _ => (wins, payout_multiple),
+            }
+        });
+    let probability = winning_values as f64 / RNG_VALUES as f64;
+
+    Odds {
+        winning_pockets,
+        winning_values,
+        probability,
+        payout_multiple,
+        expected_value: probability * (payout_multiple as f64 + 1.0) - 1.0,
+    }
+}
+
+/// Betting progression, sizes each spin of an autoplay from the previous outcomes.
+/// The bets of a spin at progression `step` are the base bets times `multiple(step)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[near(serializers = [json, borsh])]
+pub enum Strategy {
+    /// doubles after a loss, back to the base bets after a win
+    Martingale,
+    /// one unit up after a loss, one unit down after a win
+    DAlembert,
+    /// one step up the Fibonacci sequence after a loss, two steps down after a win
+    Fibonacci,
+}
+
+impl Strategy {
+    /// Stake multiple of the base bets at progression `step`.
+    pub fn multiple(&self, step: u32) -> u128 {
+        match self {
+            Strategy::Martingale => 1u128.checked_shl(step).expect("multiple overflow"),
+            Strategy::DAlembert => step as u128 + 1,
+            Strategy::Fibonacci => {
+                let (mut current, mut next) = (1u128, 1u128);
+                for _ in 0..step {
+                    (current, next) = (next, current.checked_add(next).expect("multiple overflow"));
+                }
+                current
+            }
+        }
+    }
+
+    /// Progression step after a spin at `step`, `won` when it paid more than it staked.
+    pub fn next_step(&self, step: u32, won: bool) -> u32 {
+        match (self, won) {
+            (_, false) => step + 1,
+            (Strategy::Martingale, true) => 0,
+            (Strategy::DAlembert, true) => step.saturating_sub(1),
+            (Strategy::Fibonacci, true) => step.saturating_sub(2),
+        }
+    }
+
+    /// `bets` scaled to progression `step`.
+    pub fn scale(&self, bets: &[Bet], step: u32) -> Vec<Bet> {
+        let multiple = self.multiple(step);
+        bets.iter()
+            .map(|bet| Bet {
+                amount: NearToken::from_yoctonear(
+                    bet.amount
+                        .as_yoctonear()
+                        .checked_mul(multiple)
+                        .expect("bet.amount overflow"),
+                ),
+                ..bet.clone()
+            })
+            .collect()
+    }
+
+    /// Bets of `spins` spins that all lose, no other path stakes more on any spin.
+    pub fn worst_case(&self, bets: &[Bet], spins: u8) -> Vec<Vec<Bet>> {
+        (0..spins as u32)
+            .map(|step| self.scale(bets, step))
+            .collect()
+    }
+}
+
 // consts for wheel, bet index to numbers
 
 // starts with red 32, ends with black 26
@@ -0,0 +1,220 @@
+use crate::*;
+
+#[derive(Clone, Default)]
+#[near(serializers = [borsh])]
+pub struct KindStats {
+    pub count: u64,
+    pub wagered: u128,
+    pub paid: u128,
+}
+
+#[near(serializers = [borsh])]
+pub struct TokenStats {
+    pub spins: u64,
+    pub bets: u64,
+    pub wagered: u128,
+    pub paid: u128,
+    // indexed by `BetKind as usize`
+    pub kinds: Vec<KindStats>,
+}
+
+impl TokenStats {
+    pub fn new() -> Self {
+        Self {
+            spins: 0,
+            bets: 0,
+            wagered: 0,
+            paid: 0,
+            kinds: vec![KindStats::default(); roulette::BetKind::ALL.len()],
+        }
+    }
+
+    pub fn add_wager(&mut self, kind: roulette::BetKind, amount: u128) {
+        self.bets += 1;
+        self.wagered = self.wagered.checked_add(amount).expect("wagered overflow");
+
+        let kind_stats = &mut self.kinds[kind as usize];
+        kind_stats.count += 1;
+        kind_stats.wagered = kind_stats
+            .wagered
+            .checked_add(amount)
+            .expect("wagered overflow");
+    }
+
+    pub fn add_spin(&mut self, bets: &[roulette::Bet]) {
+        self.spins += 1;
+        for bet in bets {
+            self.add_wager(bet.kind, bet.amount.as_yoctonear());
+        }
+    }
+
+    /// Takes back the wagers of a spin that was refunded instead of played.
+    pub fn remove_spin(&mut self, bets: &[roulette::Bet]) {
+        self.spins -= 1;
+        for bet in bets {
+            let amount = bet.amount.as_yoctonear();
+            self.bets -= 1;
+            self.wagered -= amount;
+
+            let kind_stats = &mut self.kinds[bet.kind as usize];
+            kind_stats.count -= 1;
+            kind_stats.wagered -= amount;
+        }
+    }
+
+    pub fn add_payout(&mut self, kind: roulette::BetKind, amount: u128) {
+        self.paid = self.paid.checked_add(amount).expect("paid overflow");
+
+        let kind_stats = &mut self.kinds[kind as usize];
+        kind_stats.paid = kind_stats.paid.checked_add(amount).expect("paid overflow");
+    }
+}
+
+/// Realised house edge in basis points, negative when players are ahead.
+fn house_edge_bps(wagered: u128, paid: u128) -> i64 {
+    if wagered == 0 {
+        return 0;
+    }
+    ((wagered as i128 - paid as i128) * 10_000 / wagered as i128) as i64
+}
+
+#[near(serializers = [json])]
+pub struct KindStatsView {
+    pub kind: roulette::BetKind,
+    pub count: U64,
+    pub wagered: U128,
+    pub paid: U128,
+    pub house_edge_bps: i64,
+}
+
+#[near(serializers = [json])]
+pub struct TokenStatsView {
+    pub token_id: AccountId,
+    pub spins: U64,
+    pub bets: U64,
+    pub wagered: U128,
+    pub paid: U128,
+    pub house_edge_bps: i64,
+    pub kinds: Vec<KindStatsView>,
+}
+
+/// Exact expectation of a layout over the random byte values, see `roulette::Odds`.
+#[near(serializers = [json])]
+pub struct LayoutOdds {
+    pub wagered: U128,
+    pub expected_payout: U128, // rounded down
+    pub expected_value: I128,  // for the player
+    pub house_edge: f64,
+}
+
+#[near]
+impl Contract {
+    /// Odds of the `spins` argument of `spin_with_near` before placing it.
+    pub fn get_layout_odds(&self, spins: Vec<Vec<roulette::Bet>>) -> LayoutOdds {
+        let rng_values = roulette::RNG_VALUES as u128;
+        let mut wagered: u128 = 0;
+        // expected payout times `RNG_VALUES`, exact
+        let mut payout_sum: u128 = 0;
+        for bet in spins.iter().flatten() {
+            require!(roulette::bet_legal(bet), "illegal bet");
+            let amount = bet.amount.as_yoctonear();
+            let odds = roulette::odds(bet);
+
+            wagered = wagered.checked_add(amount).expect("wagered overflow");
+            payout_sum = amount
+                .checked_mul((odds.payout_multiple as u128 + 1) * odds.winning_values as u128)
+                .and_then(|payout| payout_sum.checked_add(payout))
+                .expect("payout overflow");
+        }
+
+        let expected_payout = payout_sum / rng_values;
+        let house_edge = match wagered {
+            0 => 0.0,
+            wagered => {
+                let wagered_sum = wagered as f64 * rng_values as f64;
+                (wagered_sum - payout_sum as f64) / wagered_sum
+            }
+        };
+        LayoutOdds {
+            wagered: U128(wagered),
+            expected_payout: U128(expected_payout),
+            expected_value: I128(expected_payout as i128 - wagered as i128),
+            house_edge,
+        }
+    }
+
+    pub fn stats_v2(&self, token_id: AccountId) -> TokenStatsView {
+        let empty = TokenStats::new();
+        let token_stats = self.token_stats.get(&token_id).unwrap_or(&empty);
+
+        TokenStatsView {
+            spins: U64(token_stats.spins),
+            bets: U64(token_stats.bets),
+            wagered: U128(token_stats.wagered),
+            paid: U128(token_stats.paid),
+            house_edge_bps: house_edge_bps(token_stats.wagered, token_stats.paid),
+            kinds: roulette::BetKind::ALL
+                .iter()
+                .zip(&token_stats.kinds)
+                .map(|(kind, kind_stats)| KindStatsView {
+                    kind: *kind,
+                    count: U64(kind_stats.count),
+                    wagered: U128(kind_stats.wagered),
+                    paid: U128(kind_stats.paid),
+                    house_edge_bps: house_edge_bps(kind_stats.wagered, kind_stats.paid),
+                })
+                .collect(),
+            token_id,
+        }
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Takes spins that were refunded instead of played out of the global and token stats.
+    pub(crate) fn remove_spins(&mut self, token_id: &AccountId, spins: &[Vec<roulette::Bet>]) {
+        if spins.is_empty() {
+            return;
+        }
+        let token_stats = self.token_stats.get_mut(token_id).expect("no token stats");
+        for bets in spins {
+            token_stats.remove_spin(bets);
+            self.bets -= bets.len() as u128;
+            self.spins -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bet(kind: roulette::BetKind, amount: u128, number: u8) -> roulette::Bet {
+        roulette::Bet {
+            kind,
+            amount: NearToken::from_yoctonear(amount),
+            number,
+        }
+    }
+
+    #[test]
+    fn layout_odds_are_exact() {
+        let contract = Contract::default();
+        let odds = contract.get_layout_odds(vec![
+            // 125 of 256 values
+            vec![bet(roulette::BetKind::Red, 256, 0)],
+            vec![
+                // wheel index 8, 7 values
+                bet(roulette::BetKind::Straight, 256, 17),
+                // 26 and 35 take 6 values, the other ten 7
+                bet(roulette::BetKind::Dozen, 512, 2),
+            ],
+        ]);
+
+        assert_eq!(odds.wagered.0, 1_024);
+        assert_eq!(odds.expected_payout.0, 250 + 252 + 492);
+        assert_eq!(odds.expected_value.0, -30);
+        assert_eq!(odds.house_edge, 30.0 / 1_024.0);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::*;
+use near_sdk::borsh::BorshSerialize;
+
+/// Trie overhead and key of one map entry, on top of its borsh value.
+pub const STORAGE_ENTRY_BYTES: u64 = 100;
+
+/// NEAR a player deposited for the storage their spins hold: pending spins,
+/// history records and the entries of their first bet.
+#[derive(Clone, Default)]
+#[near(serializers = [borsh])]
+pub struct StorageBalance {
+    pub deposit: u128,
+    pub used_bytes: u64,
+}
+
+#[near(serializers = [json])]
+pub struct StorageBalanceView {
+    pub total: U128,
+    pub available: U128,
+    pub used_bytes: U64,
+}
+
+impl StorageBalance {
+    fn available(&self) -> u128 {
+        self.deposit.saturating_sub(byte_cost(self.used_bytes))
+    }
+}
+
+/// Bytes `value` takes as one map entry.
+pub fn entry_bytes(value: &impl BorshSerialize) -> u64 {
+    near_sdk::borsh::to_vec(value)
+        .expect("storage serialization failed")
+        .len() as u64
+        + STORAGE_ENTRY_BYTES
+}
+
+fn byte_cost(bytes: u64) -> u128 {
+    env::storage_byte_cost().as_yoctonear() * bytes as u128
+}
+
+#[near]
+impl Contract {
+    pub fn get_storage_balance(&self, account_id: AccountId) -> StorageBalanceView {
+        let balance = self
+            .storage_balances
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default();
+
+        StorageBalanceView {
+            total: U128(balance.deposit),
+            available: U128(balance.available()),
+            used_bytes: U64(balance.used_bytes),
+        }
+    }
+
+    /// Adds the attached NEAR to the storage deposit of `account_id`, the caller by default.
+    /// Spins are only accepted while it covers their pending spin and history records.
+    #[payable]
+    pub fn deposit_storage(&mut self, account_id: Option<AccountId>) {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let balance = self.storage_balances.entry(account_id).or_default();
+        balance.deposit = balance
+            .deposit
+            .checked_add(env::attached_deposit().as_yoctonear())
+            .expect("storage deposit overflow");
+    }
+
+    /// Withdraws storage deposit not held by pending spins or history, all of it by default.
+    pub fn withdraw_storage(&mut self, amount: Option<U128>) -> Promise {
+        self.assert_withdrawals_not_paused();
+        let account_id = env::predecessor_account_id();
+        let balance = self
+            .storage_balances
+            .get_mut(&account_id)
+            .expect("no storage deposit");
+        let available = balance.available();
+        let amount = amount.map_or(available, |amount| amount.0);
+        require!(
+            amount <= available,
+            "amount exceeds available storage deposit"
+        );
+        balance.deposit -= amount;
+
+        self.pay_out(bankroll::near_token_id(), account_id, amount)
+            .expect("nothing to withdraw")
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Holds `bytes` of the player's storage deposit.
+    pub(crate) fn charge_storage(&mut self, account_id: &AccountId, bytes: u64) {
+        let balance = self
+            .storage_balances
+            .get_mut(account_id)
+            .expect("storage deposit too low");
+        balance.used_bytes += bytes;
+        require!(
+            byte_cost(balance.used_bytes) <= balance.deposit,
+            "storage deposit too low"
+        );
+    }
+
+    /// Frees storage held by `charge_storage`, e.g. of a settled spin.
+    pub(crate) fn release_storage(&mut self, account_id: &AccountId, bytes: u64) {
+        if let Some(balance) = self.storage_balances.get_mut(account_id) {
+            balance.used_bytes = balance.used_bytes.saturating_sub(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit_storage, setup, setup_with_deposit, WAGER};
+    use near_sdk::test_utils::accounts;
+
+    fn spin_red(contract: &mut Contract) {
+        setup_with_deposit(accounts(1), WAGER);
+        let bet = roulette::Bet {
+            kind: roulette::BetKind::Red,
+            amount: NearToken::from_yoctonear(WAGER),
+            number: 0,
+        };
+        contract.spin_with_near(vec![vec![bet]], None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage deposit too low")]
+    fn spin_needs_storage_deposit() {
+        let mut contract = Contract::default();
+        spin_red(&mut contract);
+    }
+
+    #[test]
+    fn settled_spin_holds_its_history_record() {
+        setup(accounts(0));
+        let mut contract = Contract::default();
+        deposit_storage(&mut contract, accounts(1));
+        spin_red(&mut contract);
+        let pending_bytes = contract.get_storage_balance(accounts(1)).used_bytes.0;
+        assert!(pending_bytes > 0);
+
+        setup(accounts(0));
+        contract.mpc_callback(Ok(crate::tests::signature([0; 32])), 0);
+
+        let used_bytes = contract.get_storage_balance(accounts(1)).used_bytes.0;
+        let record_bytes = contract.get_spin(U64(0)).unwrap().storage_bytes();
+        assert!(used_bytes < pending_bytes);
+        assert!(used_bytes >= record_bytes);
+
+        // the limit drops the first record for the next one
+        contract.set_history_limit(Some(1));
+        spin_red(&mut contract);
+        setup(accounts(0));
+        contract.mpc_callback(Ok(crate::tests::signature([0; 32])), 1);
+        assert!(contract.get_spin(U64(0)).is_none());
+        assert_eq!(
+            contract.get_storage_balance(accounts(1)).used_bytes.0,
+            used_bytes
+        );
+    }
+
+    #[test]
+    fn withdraw_only_available_deposit() {
+        let mut contract = Contract::default();
+        deposit_storage(&mut contract, accounts(1));
+        spin_red(&mut contract);
+        let balance = contract.get_storage_balance(accounts(1));
+
+        setup(accounts(1));
+        contract.withdraw_storage(None);
+
+        let after = contract.get_storage_balance(accounts(1));
+        assert_eq!(after.total.0, balance.total.0 - balance.available.0);
+        assert_eq!(after.available.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "amount exceeds available storage deposit")]
+    fn withdraw_above_available_deposit() {
+        let mut contract = Contract::default();
+        deposit_storage(&mut contract, accounts(1));
+        spin_red(&mut contract);
+        let total = contract.get_storage_balance(accounts(1)).total;
+
+        setup(accounts(1));
+        contract.withdraw_storage(Some(total));
+    }
+}
@@ -0,0 +1,426 @@
+//! Contract flows driven through a mocked `VMContext`, see the modules for feature tests.
+
+use crate::*;
+use chain_signature::{SignResponse, SignatureScheme};
+use ft::FungibleTokenReceiver;
+use near_sdk::mock::MockAction;
+use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+use near_sdk::testing_env;
+use omni_transaction::signer::types::{
+    SerializableAffinePoint, SerializableScalar, SignatureResponse,
+};
+
+pub(crate) const BANKROLL: u128 = 100_000_000_000_000_000_000_000_000;
+pub(crate) const WAGER: u128 = 1_000_000_000_000_000_000_000_000;
+const USDC: &str = "usdc.fakes.testnet";
+
+/// MPC response whose random bytes are `s`, spin i lands on the pocket of s[i].
+/// 0 lands on green 0 and 1 on red 32.
+pub(crate) fn signature(s: [u8; 32]) -> SignResponse {
+    SignResponse::Ecdsa(SignatureResponse {
+        big_r: SerializableAffinePoint {
+            affine_point: format!("02{}", "00".repeat(32)),
+        },
+        s: SerializableScalar {
+            scalar: hex::encode(s),
+        },
+        recovery_id: 0,
+    })
+}
+
+/// Signature landing the first spin on `rng_val`.
+fn first_spin(rng_val: u8) -> SignResponse {
+    let mut s = [0; 32];
+    s[0] = rng_val;
+    signature(s)
+}
+
+/// Context of a call from `predecessor_id` to the contract on `accounts(0)`.
+pub(crate) fn context(predecessor_id: AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(accounts(0))
+        .predecessor_account_id(predecessor_id);
+    builder
+}
+
+/// Calls from `predecessor_id` to the contract on `accounts(0)`.
+pub(crate) fn setup(predecessor_id: AccountId) {
+    setup_with_deposit(predecessor_id, 0);
+}
+
+pub(crate) fn setup_with_deposit(predecessor_id: AccountId, deposit: u128) {
+    testing_env!(context(predecessor_id)
+        .attached_deposit(NearToken::from_yoctonear(deposit))
+        .build());
+}
+
+/// 1 NEAR, storage for ~100 kB of pending spins and history.
+pub(crate) fn deposit_storage(contract: &mut Contract, account_id: AccountId) {
+    setup_with_deposit(account_id.clone(), 1_000_000_000_000_000_000_000_000);
+    contract.deposit_storage(Some(account_id));
+}
+
+/// (receiver, action) of the receipts created since the last `setup`,
+/// the action is `transfer <yocto>` or the called method.
+fn created_actions() -> Vec<(String, String)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let receiver_id = receipt.receiver_id.to_string();
+            receipt.actions.into_iter().map(move |action| {
+                let action = match action {
+                    MockAction::Transfer { deposit, .. } => {
+                        format!("transfer {}", deposit.as_yoctonear())
+                    }
+                    MockAction::FunctionCallWeight { method_name, .. } => {
+                        String::from_utf8(method_name).unwrap()
+                    }
+                    action => format!("{:?}", action),
+                };
+                (receiver_id.clone(), action)
+            })
+        })
+        .collect()
+}
+
+/// Arguments of the `sign` calls created since the last `setup`.
+pub(crate) fn sign_requests() -> Vec<serde_json::Value> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| receipt.actions)
+        .filter_map(|action| match action {
+            MockAction::FunctionCallWeight {
+                method_name, args, ..
+            } if method_name == b"sign" => {
+                let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                Some(args["request"].clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn action(receiver_id: &str, action: &str) -> (String, String) {
+    (receiver_id.to_owned(), action.to_owned())
+}
+
+fn red(amount: u128) -> Vec<Vec<roulette::Bet>> {
+    vec![vec![roulette::Bet {
+        kind: roulette::BetKind::Red,
+        amount: NearToken::from_yoctonear(amount),
+        number: 0,
+    }]]
+}
+
+/// Player `accounts(1)` bets `WAGER` on red with NEAR, the callback runs from the contract.
+fn spin_red_with_near(contract: &mut Contract) {
+    deposit_storage(contract, accounts(1));
+    setup_with_deposit(accounts(1), WAGER);
+    contract.spin_with_near(red(WAGER), None, None, None);
+    setup(accounts(0));
+}
+
+#[test]
+fn spin_with_near_requests_signature() {
+    let mut contract = Contract::default();
+    deposit_storage(&mut contract, accounts(1));
+    setup_with_deposit(accounts(1), WAGER);
+    contract.spin_with_near(red(WAGER), None, None, None);
+
+    let near_token_id = bankroll::near_token_id();
+    assert!(contract.get_pending_spin(U64(0)).is_some());
+    assert_eq!(
+        contract.get_bankroll(near_token_id.clone()).0,
+        BANKROLL + WAGER
+    );
+    assert_eq!(contract.get_liability(near_token_id.clone()).0, 2 * WAGER);
+    let stats = contract.stats_v2(near_token_id);
+    assert_eq!(stats.spins.0, 1);
+    assert_eq!(stats.wagered.0, WAGER);
+    assert_eq!(
+        created_actions(),
+        [
+            action("v1.signer", "sign"),
+            action(accounts(0).as_str(), "mpc_callback")
+        ]
+    );
+}
+
+#[test]
+fn spin_uses_configured_signer() {
+    setup(accounts(0));
+    let mut contract = Contract::default();
+    contract.set_mpc_contract_id("mock-signer.test.near".parse().unwrap());
+
+    deposit_storage(&mut contract, accounts(1));
+    setup_with_deposit(accounts(1), WAGER);
+    contract.spin_with_near(red(WAGER), None, None, None);
+
+    assert_eq!(
+        created_actions()[0],
+        action("mock-signer.test.near", "sign")
+    );
+}
+
+#[test]
+#[should_panic(expected = "only owner")]
+fn only_owner_sets_signer() {
+    let mut contract = Contract::default();
+    setup(accounts(1));
+    contract.set_mpc_contract_id(accounts(1));
+}
+
+#[test]
+fn eddsa_spin_records_scheme() {
+    setup(accounts(0));
+    let mut contract = Contract::default();
+    contract.set_signature_scheme(SignatureScheme::Eddsa);
+    spin_red_with_near(&mut contract);
+
+    let signature: Vec<u8> = (0..64).collect();
+    let response: SignResponse =
+        serde_json::from_value(serde_json::json!({ "scheme": "Ed25519", "signature": signature }))
+            .unwrap();
+    assert_eq!(response.scheme(), SignatureScheme::Eddsa);
+    let entropy = env::keccak512(&signature);
+    contract.mpc_callback(Ok(response), 0);
+
+    let record = contract.get_spin(U64(0)).unwrap();
+    assert_eq!(record.scheme, SignatureScheme::Eddsa);
+    assert_eq!(record.number, roulette::pocket(entropy[0]));
+}
+
+#[test]
+fn ecdsa_response_parses_with_scheme_tag() {
+    let response: SignResponse = serde_json::from_value(serde_json::json!({
+        "scheme": "Secp256k1",
+        "big_r": { "affine_point": format!("03{}", "11".repeat(32)) },
+        "s": { "scalar": "22".repeat(32) },
+        "recovery_id": 1,
+    }))
+    .unwrap();
+
+    assert_eq!(response.scheme(), SignatureScheme::Ecdsa);
+    let entropy = response.entropy();
+    assert_eq!(entropy.len(), 64);
+    assert_eq!(entropy[0], 0x22);
+    assert_eq!(entropy[32], 0x11);
+}
+
+#[test]
+fn spins_in_one_block_sign_different_payloads() {
+    let mut contract = Contract::default();
+    deposit_storage(&mut contract, accounts(1));
+    setup_with_deposit(accounts(1), WAGER);
+    contract.spin_with_near(red(WAGER), None, None, None);
+    contract.spin_with_near(red(WAGER), None, None, None);
+
+    let requests = sign_requests();
+    assert_eq!(requests.len(), 2);
+    assert_ne!(requests[0]["payload_v2"], requests[1]["payload_v2"]);
+    for request in requests {
+        assert_eq!(request["path"], format!("{}/spin", accounts(0)));
+    }
+}
+
+#[test]
+#[should_panic(expected = "deposit != bet amount")]
+fn spin_with_near_requires_exact_deposit() {
+    let mut contract = Contract::default();
+    setup_with_deposit(accounts(1), WAGER + 1);
+    contract.spin_with_near(red(WAGER), None, None, None);
+}
+
+#[test]
+fn winning_callback_pays_player() {
+    let mut contract = Contract::default();
+    spin_red_with_near(&mut contract);
+
+    let results = contract.mpc_callback(Ok(first_spin(1)), 0);
+
+    let near_token_id = bankroll::near_token_id();
+    assert_eq!(results, [[(true, 32, true, 1)]]);
+    assert!(contract.get_pending_spin(U64(0)).is_none());
+    assert_eq!(
+        contract.get_bankroll(near_token_id.clone()).0,
+        BANKROLL - WAGER
+    );
+    assert_eq!(contract.get_liability(near_token_id.clone()).0, 0);
+    assert_eq!(contract.stats_v2(near_token_id).paid.0, 2 * WAGER);
+    assert_eq!(contract.get_spin(U64(0)).unwrap().number, 32);
+    assert_eq!(contract.get_recent_numbers(None)[0].number, 32);
+    assert_eq!(
+        created_actions(),
+        [
+            action(accounts(1).as_str(), &format!("transfer {}", 2 * WAGER)),
+            action(accounts(0).as_str(), "resolve_payout")
+        ]
+    );
+}
+
+#[test]
+fn held_winnings_become_claimable() {
+    let mut contract = Contract::default();
+    setup(accounts(1));
+    contract.set_holding_winnings(true);
+    spin_red_with_near(&mut contract);
+
+    contract.mpc_callback(Ok(first_spin(1)), 0);
+
+    let near_token_id = bankroll::near_token_id();
+    assert!(created_actions().is_empty());
+    assert_eq!(
+        contract.get_claimable(accounts(1), near_token_id.clone()).0,
+        2 * WAGER
+    );
+    assert_eq!(contract.get_bankroll(near_token_id).0, BANKROLL - WAGER);
+}
+
+#[test]
+fn losing_callback_keeps_wager() {
+    let mut contract = Contract::default();
+    spin_red_with_near(&mut contract);
+
+    let results = contract.mpc_callback(Ok(first_spin(0)), 0);
+
+    let near_token_id = bankroll::near_token_id();
+    assert_eq!(results, [[(false, 0, false, 0)]]);
+    assert_eq!(
+        contract.get_bankroll(near_token_id.clone()).0,
+        BANKROLL + WAGER
+    );
+    assert_eq!(contract.get_liability(near_token_id.clone()).0, 0);
+    assert_eq!(contract.stats_v2(near_token_id).paid.0, 0);
+    assert!(created_actions().is_empty());
+}
+
+#[test]
+fn failed_signature_refunds_player() {
+    let mut contract = Contract::default();
+    spin_red_with_near(&mut contract);
+
+    let results = contract.mpc_callback(Err(PromiseError::Failed), 0);
+
+    assert_eq!(results, [[(false, 0, false, 0)]]);
+    assert_eq!(contract.get_bankroll(bankroll::near_token_id()).0, BANKROLL);
+    assert!(contract.get_spin(U64(0)).is_none());
+    assert_eq!(
+        created_actions(),
+        [
+            action(accounts(1).as_str(), &format!("transfer {}", WAGER)),
+            action(accounts(0).as_str(), "resolve_payout")
+        ]
+    );
+}
+
+#[test]
+fn ft_spin_pays_in_token() {
+    let usdc: AccountId = USDC.parse().unwrap();
+    setup(usdc.clone());
+    let mut contract = Contract::default();
+    deposit_storage(&mut contract, accounts(1));
+
+    // empty msg from the owner funds the bankroll
+    setup(usdc.clone());
+    contract.ft_on_transfer(accounts(0), U128(1_000), String::new());
+    assert_eq!(contract.get_bankroll(usdc.clone()).0, 1_000);
+
+    let msg = serde_json::json!({ "spins": red(10) }).to_string();
+    contract.ft_on_transfer(accounts(1), U128(10), msg);
+    assert_eq!(contract.get_liability(usdc.clone()).0, 20);
+    assert_eq!(
+        created_actions(),
+        [
+            action("v1.signer", "sign"),
+            action(accounts(0).as_str(), "mpc_callback")
+        ]
+    );
+
+    setup(accounts(0));
+    contract.mpc_callback(Ok(first_spin(1)), 0);
+
+    assert_eq!(contract.get_bankroll(usdc.clone()).0, 990);
+    assert_eq!(contract.stats_v2(usdc).paid.0, 20);
+    assert_eq!(
+        created_actions(),
+        [
+            action(USDC, "ft_transfer"),
+            action(accounts(0).as_str(), "resolve_payout")
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "only owner funds the bankroll")]
+fn ft_empty_msg_from_player_is_refunded() {
+    let mut contract = Contract::default();
+    setup(USDC.parse().unwrap());
+    contract.ft_on_transfer(accounts(1), U128(1_000), String::new());
+}
+
+#[test]
+#[should_panic(expected = "only owner")]
+fn only_owner_funds_near_bankroll() {
+    let mut contract = Contract::default();
+    setup_with_deposit(accounts(1), WAGER);
+    contract.fund_bankroll();
+}
+
+#[test]
+#[should_panic(expected = "zero bet")]
+fn zero_bet_is_rejected() {
+    let mut contract = Contract::default();
+    deposit_storage(&mut contract, accounts(1));
+    setup(accounts(1));
+    contract.spin_with_near(red(0), None, None, None);
+}
+
+#[test]
+#[should_panic(expected = "illegal bet")]
+fn illegal_bet_is_rejected_before_payout_math() {
+    let mut contract = Contract::default();
+    setup_with_deposit(accounts(1), WAGER);
+    let spins = vec![vec![roulette::Bet {
+        kind: roulette::BetKind::Straight,
+        amount: NearToken::from_yoctonear(WAGER),
+        number: 200,
+    }]];
+    contract.spin_with_near(spins, None, None, None);
+}
+
+#[test]
+#[should_panic(expected = "token not supported")]
+fn ft_on_transfer_rejects_unknown_token() {
+    let mut contract = Contract::default();
+    setup(accounts(3));
+    contract.ft_on_transfer(accounts(1), U128(10), String::new());
+}
+
+#[test]
+fn failed_withdrawal_restores_bankroll() {
+    let usdc: AccountId = USDC.parse().unwrap();
+    setup(usdc.clone());
+    let mut contract = Contract::default();
+    contract.ft_on_transfer(accounts(0), U128(1_000), String::new());
+
+    setup(accounts(0));
+    contract.withdraw_bankroll(usdc.clone(), U128(400), accounts(2));
+    assert_eq!(contract.get_bankroll(usdc.clone()).0, 600);
+    assert_eq!(
+        created_actions(),
+        [
+            action(USDC, "ft_transfer"),
+            action(accounts(0).as_str(), "resolve_withdraw_bankroll")
+        ]
+    );
+
+    assert!(!contract.resolve_withdraw_bankroll(
+        Err(PromiseError::Failed),
+        usdc.clone(),
+        U128(400)
+    ));
+    assert_eq!(contract.get_bankroll(usdc.clone()).0, 1_000);
+    assert!(contract.resolve_withdraw_bankroll(Ok(()), usdc.clone(), U128(400)));
+    assert_eq!(contract.get_bankroll(usdc).0, 1_000);
+}
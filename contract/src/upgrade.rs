@@ -0,0 +1,296 @@
+use crate::*;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::CryptoHash;
+
+// staged wasm is kept out of the contract state, it is only read on deploy
+const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
+const MIGRATE_GAS: Gas = Gas::from_tgas(100);
+pub const DEFAULT_UPGRADE_DELAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[near(serializers = [borsh])]
+pub struct StagedUpgrade {
+    pub code_hash: CryptoHash,
+    pub deployable_at: u64, // block timestamp, ns
+    pub approved: bool,
+}
+
+#[near(serializers = [json])]
+pub struct StagedUpgradeView {
+    pub code_hash: Base58CryptoHash,
+    pub deployable_at: U64,
+    pub approved: bool,
+}
+
+/// Lower upgrade delay, applied once the delay it replaces has passed.
+#[near(serializers = [json, borsh])]
+pub struct LoweredDelay {
+    pub delay_ns: U64,
+    pub effective_at: U64, // block timestamp, ns
+}
+
+#[near]
+impl Contract {
+    pub fn get_staged_upgrade(&self) -> Option<StagedUpgradeView> {
+        self.staged_upgrade
+            .as_ref()
+            .map(|staged| StagedUpgradeView {
+                code_hash: staged.code_hash.into(),
+                deployable_at: U64(staged.deployable_at),
+                approved: staged.approved,
+            })
+    }
+
+    pub fn get_upgrade_delay(&self) -> U64 {
+        U64(self.upgrade_delay())
+    }
+
+    pub fn get_lowered_upgrade_delay(&self) -> Option<&LoweredDelay> {
+        self.lowered_upgrade_delay.as_ref()
+    }
+
+    /// Applies to upgrades staged afterwards. A higher delay applies at once,
+    /// a lower one only after the current delay has passed.
+    pub fn set_upgrade_delay(&mut self, delay_ns: U64) {
+        self.assert_owner();
+        let current_delay = self.upgrade_delay();
+        if delay_ns.0 >= current_delay {
+            self.upgrade_delay_ns = delay_ns.0;
+            self.lowered_upgrade_delay = None;
+            return;
+        }
+
+        let effective_at = env::block_timestamp() + current_delay;
+        self.upgrade_delay_ns = current_delay;
+        self.lowered_upgrade_delay = Some(LoweredDelay {
+            delay_ns,
+            effective_at: U64(effective_at),
+        });
+        log!(
+            "upgrade delay lowered to {}, effective at {}",
+            delay_ns.0,
+            effective_at
+        );
+    }
+
+    pub fn get_upgrade_approver(&self) -> Option<AccountId> {
+        self.upgrade_approver_id.clone()
+    }
+
+    /// Account, e.g. a DAO, that has to approve staged code before it is deployed.
+    /// Set by the owner while there is none, afterwards only the approver can replace it.
+    pub fn set_upgrade_approver(&mut self, approver_id: Option<AccountId>) {
+        match &self.upgrade_approver_id {
+            Some(current_id) => require!(
+                env::predecessor_account_id() == *current_id,
+                "only upgrade approver"
+            ),
+            None => self.assert_owner(),
+        }
+        self.upgrade_approver_id = approver_id;
+    }
+
+    /// Approves the staged code, `code_hash` must match it.
+    pub fn approve_upgrade(&mut self, code_hash: Base58CryptoHash) {
+        require!(
+            self.upgrade_approver_id.as_ref() == Some(&env::predecessor_account_id()),
+            "only upgrade approver"
+        );
+        let staged = self.staged_upgrade.as_mut().expect("no staged upgrade");
+        require!(
+            staged.code_hash == CryptoHash::from(code_hash),
+            "code hash does not match staged code"
+        );
+        staged.approved = true;
+    }
+
+    /// Stores new contract code, replacing any staged code and its approval.
+    /// The code can be deployed with `deploy_upgrade` once the upgrade delay has passed
+    /// and, when there is an upgrade approver, it approved the code.
+    pub fn stage_upgrade(&mut self, #[serializer(borsh)] code: Vec<u8>) {
+        self.assert_owner();
+        require!(!code.is_empty(), "empty code");
+
+        let code_hash = env::sha256_array(&code);
+        env::storage_write(STAGED_CODE_KEY, &code);
+
+        let deployable_at = env::block_timestamp() + self.upgrade_delay();
+        self.staged_upgrade = Some(StagedUpgrade {
+            code_hash,
+            deployable_at,
+            approved: false,
+        });
+
+        log!(
+            "staged upgrade {}, deployable at {}",
+            String::from(&Base58CryptoHash::from(code_hash)),
+            deployable_at
+        );
+    }
+
+    pub fn cancel_upgrade(&mut self) {
+        self.assert_owner();
+        env::storage_remove(STAGED_CODE_KEY);
+        self.staged_upgrade = None;
+    }
+
+    /// Deploys the staged code on this account and migrates the state.
+    pub fn deploy_upgrade(&mut self) -> Promise {
+        self.assert_owner();
+        let staged = self.staged_upgrade.take().expect("no staged upgrade");
+        require!(
+            env::block_timestamp() >= staged.deployable_at,
+            "upgrade delay not passed"
+        );
+        require!(
+            staged.approved || self.upgrade_approver_id.is_none(),
+            "upgrade not approved"
+        );
+
+        let code = env::storage_read(STAGED_CODE_KEY).expect("staged code missing");
+        env::storage_remove(STAGED_CODE_KEY);
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_owned(),
+                vec![],
+                NearToken::from_yoctonear(0),
+                MIGRATE_GAS,
+            )
+    }
+}
+
+// internal
+
+impl Contract {
+    /// Upgrade delay at the current block, with a lowered delay applied once effective.
+    pub(crate) fn upgrade_delay(&self) -> u64 {
+        match &self.lowered_upgrade_delay {
+            Some(lowered) if env::block_timestamp() >= lowered.effective_at.0 => lowered.delay_ns.0,
+            _ => self.upgrade_delay_ns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{context, setup};
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    /// Owner calls at `block_timestamp`.
+    fn setup_at_time(block_timestamp: u64) {
+        testing_env!(context(accounts(0))
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    fn contract_with_delay(delay_ns: u64) -> Contract {
+        Contract {
+            upgrade_delay_ns: delay_ns,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stage_upgrade_waits_for_delay() {
+        setup_at_time(1_000);
+        let mut contract = contract_with_delay(500);
+        contract.stage_upgrade(vec![1, 2, 3]);
+
+        let staged = contract.get_staged_upgrade().unwrap();
+        assert_eq!(staged.code_hash, env::sha256_array(&[1, 2, 3]).into());
+        assert_eq!(staged.deployable_at.0, 1_500);
+
+        setup_at_time(1_500);
+        contract.deploy_upgrade();
+        assert!(contract.get_staged_upgrade().is_none());
+        assert!(env::storage_read(STAGED_CODE_KEY).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "upgrade delay not passed")]
+    fn deploy_upgrade_before_delay() {
+        setup_at_time(1_000);
+        let mut contract = contract_with_delay(500);
+        contract.stage_upgrade(vec![1, 2, 3]);
+
+        setup_at_time(1_499);
+        contract.deploy_upgrade();
+    }
+
+    #[test]
+    fn lower_delay_waits_for_current_delay() {
+        setup_at_time(1_000);
+        let mut contract = contract_with_delay(500);
+        contract.set_upgrade_delay(U64(0));
+
+        assert_eq!(contract.get_upgrade_delay().0, 500);
+        assert_eq!(
+            contract.get_lowered_upgrade_delay().unwrap().effective_at.0,
+            1_500
+        );
+        contract.stage_upgrade(vec![1, 2, 3]);
+        assert_eq!(
+            contract.get_staged_upgrade().unwrap().deployable_at.0,
+            1_500
+        );
+
+        setup_at_time(1_500);
+        assert_eq!(contract.get_upgrade_delay().0, 0);
+        contract.stage_upgrade(vec![4, 5, 6]);
+        contract.deploy_upgrade();
+
+        // a higher delay applies at once
+        contract.set_upgrade_delay(U64(700));
+        assert_eq!(contract.get_upgrade_delay().0, 700);
+        assert!(contract.get_lowered_upgrade_delay().is_none());
+    }
+
+    #[test]
+    fn approver_approves_staged_code() {
+        setup_at_time(1_000);
+        let mut contract = contract_with_delay(0);
+        contract.set_upgrade_approver(Some(accounts(1)));
+        contract.stage_upgrade(vec![1, 2, 3]);
+        assert!(!contract.get_staged_upgrade().unwrap().approved);
+
+        setup(accounts(1));
+        contract.approve_upgrade(env::sha256_array(&[1, 2, 3]).into());
+        assert!(contract.get_staged_upgrade().unwrap().approved);
+
+        setup_at_time(1_000);
+        contract.deploy_upgrade();
+        assert!(contract.get_staged_upgrade().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "upgrade not approved")]
+    fn deploy_upgrade_without_approval() {
+        setup_at_time(1_000);
+        let mut contract = contract_with_delay(0);
+        contract.set_upgrade_approver(Some(accounts(1)));
+        contract.stage_upgrade(vec![1, 2, 3]);
+
+        contract.deploy_upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "only upgrade approver")]
+    fn owner_cannot_replace_approver() {
+        setup_at_time(1_000);
+        let mut contract = Contract::default();
+        contract.set_upgrade_approver(Some(accounts(1)));
+        contract.set_upgrade_approver(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "only owner")]
+    fn stage_upgrade_only_owner() {
+        setup_at_time(1_000);
+        let mut contract = Contract::default();
+        contract.set_owner(accounts(1));
+        contract.stage_upgrade(vec![1, 2, 3]);
+    }
+}
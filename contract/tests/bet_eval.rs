@@ -0,0 +1,140 @@
+use contract_rs::roulette::*;
+
+use near_sdk::NearToken;
+
+// standard European layout, independent of the tables in `roulette`
+const RED_NUMBERS: [u8; 18] = [
+    1, 3, 5, 7, 9, 12, 14, 16, 18, 19, 21, 23, 25, 27, 30, 32, 34, 36,
+];
+
+fn bet(kind: BetKind, number: u8) -> Bet {
+    Bet {
+        kind,
+        amount: NearToken::from_yoctonear(1),
+        number,
+    }
+}
+
+/// Numbers a legal bet wins on, from the table geometry: row r holds 3r+1..=3r+3.
+fn expected_numbers(bet: &Bet) -> Vec<u8> {
+    let n = bet.number;
+    match bet.kind {
+        BetKind::Straight => vec![n],
+        BetKind::Split => {
+            // per row: two horizontal splits, then three vertical ones, the last row has no vertical
+            let (row, offset) = (n / 5, n % 5);
+            let first = 3 * row + 1;
+            match offset {
+                0 | 1 => vec![first + offset, first + offset + 1],
+                _ => vec![first + offset - 2, first + offset + 1],
+            }
+        }
+        BetKind::Street => (3 * n + 1..=3 * n + 3).collect(),
+        BetKind::Corner => {
+            let first = 3 * (n / 2) + 1 + n % 2;
+            vec![first, first + 1, first + 3, first + 4]
+        }
+        BetKind::SixLine => (3 * n + 1..=3 * n + 6).collect(),
+        BetKind::Column => (1..=36).filter(|number| (number - 1) % 3 == n).collect(),
+        BetKind::Dozen => (12 * n + 1..=12 * n + 12).collect(),
+        BetKind::Red => RED_NUMBERS.to_vec(),
+        BetKind::Black => (1..=36)
+            .filter(|number| !RED_NUMBERS.contains(number))
+            .collect(),
+        BetKind::Odd => (1..=36).filter(|number| number % 2 == 1).collect(),
+        BetKind::Even => (1..=36).filter(|number| number % 2 == 0).collect(),
+        BetKind::Low => (1..=18).collect(),
+        BetKind::High => (19..=36).collect(),
+    }
+}
+
+fn expected_multiple(kind: BetKind) -> u8 {
+    match kind {
+        BetKind::Straight => 35,
+        BetKind::Split => 17,
+        BetKind::Street => 11,
+        BetKind::Corner => 8,
+        BetKind::SixLine => 5,
+        BetKind::Column | BetKind::Dozen => 2,
+        BetKind::Red
+        | BetKind::Black
+        | BetKind::Odd
+        | BetKind::Even
+        | BetKind::Low
+        | BetKind::High => 1,
+    }
+}
+
+/// Every legal bet, by kind and index.
+fn legal_bets() -> Vec<Bet> {
+    BetKind::ALL
+        .iter()
+        .flat_map(|kind| (0..=255u8).map(|number| bet(*kind, number)))
+        .filter(bet_legal)
+        .collect()
+}
+
+#[test]
+fn test_wheel_covers_every_pocket_once() {
+    let mut pockets: Vec<u8> = (0..37u8).map(pocket).collect();
+    pockets.sort_unstable();
+    assert_eq!(pockets, (0..37).collect::<Vec<u8>>());
+}
+
+#[test]
+fn test_legal_indices() {
+    let count = |kind| legal_bets().iter().filter(|bet| bet.kind == kind).count();
+    assert_eq!(count(BetKind::Straight), 36);
+    assert_eq!(count(BetKind::Split), 57);
+    assert_eq!(count(BetKind::Street), 12);
+    assert_eq!(count(BetKind::Corner), 22);
+    assert_eq!(count(BetKind::SixLine), 11);
+    assert_eq!(count(BetKind::Column), 3);
+    assert_eq!(count(BetKind::Dozen), 3);
+    // outside bets ignore the number
+    assert_eq!(count(BetKind::Red), 256);
+}
+
+#[test]
+fn test_bet_eval_all_inputs() {
+    for bet in legal_bets() {
+        let expected = expected_numbers(&bet);
+
+        for rng_val in 0..=255u8 {
+            let number = pocket(rng_val);
+            let (win, eval_number, red, multiple) = bet_eval(rng_val, &bet);
+
+            assert_eq!(eval_number, number, "{:?} rng_val {}", bet, rng_val);
+            assert_eq!(red, RED_NUMBERS.contains(&number), "rng_val {}", rng_val);
+            assert_eq!(win, expected.contains(&number), "{:?} on {}", bet, number);
+            let expected_multiple = if win { expected_multiple(bet.kind) } else { 0 };
+            assert_eq!(multiple, expected_multiple, "{:?} on {}", bet, number);
+        }
+    }
+}
+
+#[test]
+fn test_colors() {
+    for number in 0..37 {
+        let expected = match number {
+            0 => Color::Green,
+            number if RED_NUMBERS.contains(&number) => Color::Red,
+            _ => Color::Black,
+        };
+        assert_eq!(number_color(number), expected, "{}", number);
+    }
+}
+
+// a bet covering k of the 37 pockets pays 36 / k units with the stake, see `odds` for the real edge
+#[test]
+fn test_payout_table_matches_european_odds() {
+    for bet in legal_bets() {
+        let winning_pockets = (0..37u8)
+            .filter(|rng_val| bet_eval(*rng_val, &bet).0)
+            .count() as u32;
+        let multiple = expected_multiple(bet.kind) as u32;
+
+        assert_eq!((multiple + 1) * winning_pockets, 36, "{:?}", bet);
+        assert_eq!(odds(&bet).winning_pockets as u32, winning_pockets);
+    }
+}
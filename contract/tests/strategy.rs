@@ -0,0 +1,90 @@
+use contract_rs::roulette::*;
+
+use near_sdk::NearToken;
+
+const STRATEGIES: [Strategy; 3] = [
+    Strategy::Martingale,
+    Strategy::DAlembert,
+    Strategy::Fibonacci,
+];
+
+fn red(amount: u128) -> Bet {
+    Bet {
+        kind: BetKind::Red,
+        amount: NearToken::from_yoctonear(amount),
+        number: 0,
+    }
+}
+
+fn multiples(strategy: Strategy, steps: u32) -> Vec<u128> {
+    (0..steps).map(|step| strategy.multiple(step)).collect()
+}
+
+#[test]
+fn test_strategy_multiples() {
+    assert_eq!(multiples(Strategy::Martingale, 6), [1, 2, 4, 8, 16, 32]);
+    assert_eq!(multiples(Strategy::DAlembert, 6), [1, 2, 3, 4, 5, 6]);
+    assert_eq!(multiples(Strategy::Fibonacci, 6), [1, 1, 2, 3, 5, 8]);
+}
+
+#[test]
+fn test_strategy_next_step() {
+    for strategy in STRATEGIES {
+        assert_eq!(strategy.next_step(3, false), 4);
+    }
+    assert_eq!(Strategy::Martingale.next_step(3, true), 0);
+    assert_eq!(Strategy::DAlembert.next_step(3, true), 2);
+    assert_eq!(Strategy::DAlembert.next_step(0, true), 0);
+    assert_eq!(Strategy::Fibonacci.next_step(3, true), 1);
+    assert_eq!(Strategy::Fibonacci.next_step(1, true), 0);
+}
+
+#[test]
+fn test_strategy_scale() {
+    let bets = vec![
+        red(10),
+        Bet {
+            kind: BetKind::Straight,
+            amount: NearToken::from_yoctonear(3),
+            number: 17,
+        },
+    ];
+    let scaled = Strategy::Martingale.scale(&bets, 2);
+
+    assert_eq!(scaled.len(), 2);
+    assert_eq!(scaled[0].kind, BetKind::Red);
+    assert_eq!(scaled[0].amount.as_yoctonear(), 40);
+    assert_eq!(scaled[1].kind, BetKind::Straight);
+    assert_eq!(scaled[1].number, 17);
+    assert_eq!(scaled[1].amount.as_yoctonear(), 12);
+}
+
+#[test]
+fn test_strategy_worst_case() {
+    let worst_case = Strategy::Martingale.worst_case(&[red(1)], 4);
+    let amounts: Vec<u128> = worst_case
+        .iter()
+        .map(|bets| bets[0].amount.as_yoctonear())
+        .collect();
+    assert_eq!(amounts, [1, 2, 4, 8]);
+}
+
+// every win/loss path of `spins` spins stakes at most the worst case on each spin
+#[test]
+fn test_strategy_worst_case_covers_all_paths() {
+    let spins = 10u8;
+    for strategy in STRATEGIES {
+        let worst_case = strategy.worst_case(&[red(1)], spins);
+
+        for path in 0..1u32 << spins {
+            let mut step = 0;
+            for (i, worst_bets) in worst_case.iter().enumerate() {
+                let staked = strategy.scale(&[red(1)], step);
+                assert!(staked[0].amount <= worst_bets[0].amount);
+
+                let won = path & (1 << i) != 0;
+                step = strategy.next_step(step, won);
+            }
+        }
+    }
+}
@@ -219,7 +219,6 @@ async function test() {
       amount: '52000000', // $52
       msg: JSON.stringify({
         spins,
-        callback_tgas: 55,
       }),
     },
     gas: 300000000000000n,
@@ -250,8 +249,9 @@ async function test() {
     }
     const spinResults = await call({
       methodName: 'spin_with_near',
-      args: { spins, callback_tgas: 3 },
+      args: { spins },
       deposit,
+      gas: 100000000000000n,
     });
     let totalMultiple = 0;
     for (const [i, betResults] of spinResults.entries()) {